@@ -0,0 +1,194 @@
+//! Pick lists and simulation of the alliance selection draft held between the qualification and
+//! playoff matches of an event
+
+use std::collections::{HashMap, HashSet};
+
+use crate::model::{
+    event::{EliminationAlliance, EventRankings, PlayoffType},
+    team::TeamKey,
+};
+
+/// Manual adjustment to how a team is treated during alliance selection
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Adjustment {
+    /// Never pick this team
+    DoNotPick,
+    /// Add the given amount to the team's value
+    Offset(f64),
+    /// The team declines any invitation to join an alliance
+    Declines,
+}
+
+/// Rules of an alliance selection draft
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelectionRules {
+    /// Number of alliances selected
+    pub alliances: usize,
+    /// Number of teams picked by each captain, in rounds that alternate direction
+    pub picks: usize,
+}
+
+impl SelectionRules {
+    /// Get the rules of the draft for an event with the given playoff format, where
+    /// `championship` is set for championship divisions, whose alliances pick a fourth team
+    /// instead of calling up backup teams
+    pub fn for_playoff_type(playoff_type: PlayoffType, championship: bool) -> Self {
+        let alliances = match playoff_type {
+            PlayoffType::Bracket16Team => 16,
+            PlayoffType::Bracket8Team
+            | PlayoffType::AvgScore8Team
+            | PlayoffType::DoubleElim8Team
+            | PlayoffType::ModernDoubleElim8Team => 8,
+            PlayoffType::RoundRobin6Team => 6,
+            PlayoffType::Bracket4Team | PlayoffType::DoubleElim4Team => 4,
+            PlayoffType::Bracket2Team | PlayoffType::Bo3Finals | PlayoffType::Bo5Finals => 2,
+            // Events with a custom or unrecognized format most often still draft 8 alliances
+            PlayoffType::Custom | PlayoffType::Unknown(_) => 8,
+        };
+        Self { alliances, picks: if championship { 3 } else { 2 } }
+    }
+}
+
+/// Result of a simulated alliance selection
+#[derive(Clone, Debug)]
+pub struct SimulatedSelection {
+    pub alliances: Vec<EliminationAlliance>,
+    /// Teams that were not picked, in rank order, which are available as backups
+    pub backups: Vec<TeamKey>,
+}
+
+/// Get the value of a team after applying its adjustment, or `None` if it must not be picked
+fn adjusted_value(team: &TeamKey, values: &HashMap<TeamKey, f64>, adjustments: &HashMap<TeamKey, Adjustment>) -> Option<f64> {
+    let value = values.get(team).copied().unwrap_or(f64::NEG_INFINITY);
+    match adjustments.get(team) {
+        Some(Adjustment::DoNotPick) => None,
+        Some(Adjustment::Offset(offset)) => Some(value + offset),
+        _ => Some(value),
+    }
+}
+
+/// Get the ranked teams in order of rank
+fn rank_order(rankings: &EventRankings) -> Vec<TeamKey> {
+    let mut ranked = rankings
+        .rankings
+        .iter()
+        .filter_map(|ranking| Some((ranking.rank.unwrap_or(u32::MAX), ranking.team_key.clone()?)))
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|(rank, _)| *rank);
+    ranked.into_iter().map(|(_, team)| team).collect()
+}
+
+/// Get every ranked team that may be picked, best first, by value after adjustments
+pub fn pick_list(rankings: &EventRankings, values: &HashMap<TeamKey, f64>, adjustments: &HashMap<TeamKey, Adjustment>) -> Vec<TeamKey> {
+    let mut list = rank_order(rankings)
+        .into_iter()
+        .filter_map(|team| Some((adjusted_value(&team, values, adjustments)?, team)))
+        .collect::<Vec<_>>();
+    // The sort is stable, so teams of equal value stay in rank order
+    list.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    list.into_iter().map(|(_, team)| team).collect()
+}
+
+/// Simulate a serpentine alliance selection draft. Each alliance is captained by the highest
+/// ranked team not yet on an alliance when its turn first comes, and every captain picks the
+/// best available team on the same pick list. Teams that decline an invitation can no longer be
+/// picked, but may still become captains
+pub fn simulate(
+    rankings: &EventRankings,
+    values: &HashMap<TeamKey, f64>,
+    adjustments: &HashMap<TeamKey, Adjustment>,
+    rules: &SelectionRules,
+) -> SimulatedSelection {
+    let ranked = rank_order(rankings);
+    let list = pick_list(rankings, values, adjustments);
+    let mut selected = HashSet::<TeamKey>::new();
+    let mut declined = HashSet::<TeamKey>::new();
+    let mut alliances = Vec::<EliminationAlliance>::with_capacity(rules.alliances);
+
+    for round in 0..rules.picks {
+        let order = (0..rules.alliances).collect::<Vec<_>>();
+        let order = match round % 2 {
+            0 => order,
+            _ => order.into_iter().rev().collect(),
+        };
+        for i in order {
+            if i == alliances.len() {
+                let Some(captain) = ranked.iter().find(|team| !selected.contains(*team)) else { break };
+                selected.insert(captain.clone());
+                alliances.push(EliminationAlliance {
+                    name: Some(format!("Alliance {}", i + 1)),
+                    backup: None,
+                    declined: Vec::new(),
+                    picks: vec![captain.clone()],
+                    status: None,
+                });
+            }
+            let Some(alliance) = alliances.get_mut(i) else { continue };
+
+            while let Some(team) = list.iter().find(|team| !selected.contains(*team) && !declined.contains(*team)).cloned() {
+                if adjustments.get(&team) == Some(&Adjustment::Declines) {
+                    declined.insert(team.clone());
+                    alliance.declined.push(team);
+                    continue
+                }
+                selected.insert(team.clone());
+                alliance.picks.push(team);
+                break
+            }
+        }
+    }
+
+    let backups = ranked
+        .into_iter()
+        .filter(|team| !selected.contains(team) && !declined.contains(team))
+        .collect();
+    SimulatedSelection { alliances, backups }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::matches::test_util::teams;
+
+    use super::*;
+
+    #[test]
+    fn serpentine_draft() {
+        let mut rankings: EventRankings = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_rankings.json")).unwrap();
+        let template = rankings.rankings[0].clone();
+        let teams = teams(1..=16);
+        rankings.rankings = teams
+            .iter()
+            .enumerate()
+            .map(|(i, team)| {
+                let mut ranking = template.clone();
+                ranking.team_key = Some(team.clone());
+                ranking.rank = Some(i as u32 + 1);
+                ranking
+            })
+            .collect();
+        // Value increases with team number, so lower ranked teams are the best picks
+        let values = teams.iter().enumerate().map(|(i, team)| (team.clone(), i as f64)).collect();
+        let adjustments = [
+            (teams[15].clone(), Adjustment::DoNotPick),
+            (teams[14].clone(), Adjustment::Declines),
+        ].into_iter().collect();
+
+        let list = pick_list(&rankings, &values, &adjustments);
+        assert_eq!(list[0], teams[14]);
+        assert!(!list.contains(&teams[15]));
+
+        assert_eq!(SelectionRules::for_playoff_type(PlayoffType::Bo3Finals, false).alliances, 2);
+        assert_eq!(SelectionRules::for_playoff_type(PlayoffType::ModernDoubleElim8Team, true).alliances, 8);
+        let rules = SelectionRules::for_playoff_type(PlayoffType::DoubleElim4Team, false);
+        let selection = simulate(&rankings, &values, &adjustments, &rules);
+        let picks = selection.alliances.iter().map(|a| a.picks.iter().map(|t| t.to_string()).collect::<Vec<_>>()).collect::<Vec<_>>();
+        assert_eq!(picks, [
+            ["frc1", "frc14", "frc7"],
+            ["frc2", "frc13", "frc8"],
+            ["frc3", "frc12", "frc9"],
+            ["frc4", "frc11", "frc10"],
+        ].map(|a| a.map(String::from).to_vec()));
+        assert_eq!(selection.alliances[0].declined, [teams[14].clone()]);
+        assert_eq!(selection.backups, [teams[4].clone(), teams[5].clone(), teams[15].clone()]);
+    }
+}
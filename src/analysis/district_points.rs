@@ -0,0 +1,293 @@
+//! Calculation of the district points earned by teams at an event, following FIRST's rules for
+//! each season
+
+use std::collections::{HashMap, HashSet};
+
+use crate::model::{
+    award::Award,
+    event::{EliminationAlliance, Event, EventDistrictPoints, EventDistrictPointsPoints, EventDistrictPointsTiebreaker, EventRankings, EventType, PlayoffLevel, PlayoffType},
+    matches::{Match, MatchWinner},
+    team::TeamKey,
+};
+
+use super::math::erfinv;
+
+/// Constant used to spread qualification points between the first and last ranked team
+const QUAL_ALPHA: f64 = 1.07;
+
+/// Award types, as defined by TBA's `AwardType` enumeration, that are worth district points
+const IMPACT_AWARD: u32 = 0;
+const ENGINEERING_INSPIRATION: u32 = 9;
+const ROOKIE_ALL_STAR: u32 = 10;
+
+/// Award types that are not judged team awards and so earn no district points: winner,
+/// finalist, Woodie Flowers, Dean's List, volunteer, highest rookie seed, and wildcard
+const NON_JUDGED_AWARDS: [u32; 7] = [1, 2, 3, 4, 5, 14, 68];
+
+/// Get the points earned by a team for finishing qualifications at the given rank out of
+/// `num_teams` teams
+pub fn qual_points(rank: u32, num_teams: u32) -> i32 {
+    let n = num_teams as f64;
+    let x = (n - 2. * rank as f64 + 2.) / (QUAL_ALPHA * n);
+    (erfinv(x) * 10. / erfinv(1. / QUAL_ALPHA) + 12.).ceil() as i32
+}
+
+/// Get the points earned by a team for being selected to the alliance with the given number,
+/// as the captain (pick 0) or a later pick
+pub fn alliance_points(alliance_number: u32, pick: usize) -> i32 {
+    match pick {
+        0 | 1 => 17 - alliance_number as i32,
+        2 => alliance_number as i32,
+        _ => 0,
+    }
+}
+
+/// Get the points earned by a team for receiving an award of the given type
+pub fn award_points(award_type: u32) -> i32 {
+    match award_type {
+        IMPACT_AWARD => 10,
+        ENGINEERING_INSPIRATION | ROOKIE_ALL_STAR => 8,
+        ty if NON_JUDGED_AWARDS.contains(&ty) => 0,
+        _ => 5,
+    }
+}
+
+/// Calculate the district points earned by each team at `event` from its qualification
+/// rankings, the alliances selected for its playoffs, its matches, and the awards given.
+/// Passing the data of an event in progress projects the points earned so far
+pub fn calculate(
+    event: &Event,
+    rankings: Option<&EventRankings>,
+    alliances: &[EliminationAlliance],
+    matches: &[Match],
+    awards: &[Award],
+) -> EventDistrictPoints {
+    let mut points = HashMap::<TeamKey, EventDistrictPointsPoints>::new();
+    fn entry<'a>(points: &'a mut HashMap<TeamKey, EventDistrictPointsPoints>, team: &TeamKey) -> &'a mut EventDistrictPointsPoints {
+        points.entry(team.clone()).or_default()
+    }
+
+    if let Some(rankings) = rankings {
+        let num_teams = rankings.rankings.len() as u32;
+        for ranking in rankings.rankings.iter() {
+            if let (Some(team), Some(rank)) = (&ranking.team_key, ranking.rank) {
+                entry(&mut points, team).qual_points += qual_points(rank, num_teams);
+            }
+        }
+    }
+
+    for (i, alliance) in alliances.iter().enumerate() {
+        for (pick, team) in alliance.picks.iter().enumerate() {
+            entry(&mut points, team).alliance_points += alliance_points(i as u32 + 1, pick);
+        }
+    }
+
+    let playoffs = matches
+        .iter()
+        .filter(|m| m.comp_level != PlayoffLevel::QM)
+        .collect::<Vec<_>>();
+    match event.simple.year.val() {
+        ..=2022 => for m in playoffs.iter() {
+            for team in winning_teams(m) {
+                entry(&mut points, team).elim_points += 5;
+            }
+        },
+        _ => {
+            let placements = placements(event, alliances, &playoffs);
+            for (team, elim_points) in placements {
+                entry(&mut points, &team).elim_points += elim_points;
+            }
+        },
+    }
+
+    for award in awards {
+        for team in award.recipient_list.iter().filter_map(|r| r.team_key.as_ref()) {
+            entry(&mut points, team).award_points += award_points(award.award_type);
+        }
+    }
+
+    let multiplier = match event.simple.event_type {
+        EventType::DistrictCmp | EventType::DistrictCmpDivision => 3,
+        _ => 1,
+    };
+    for p in points.values_mut() {
+        p.qual_points *= multiplier;
+        p.alliance_points *= multiplier;
+        p.elim_points *= multiplier;
+        p.award_points *= multiplier;
+        p.total = p.qual_points + p.alliance_points + p.elim_points + p.award_points;
+    }
+
+    let tiebreakers = points
+        .keys()
+        .map(|team| (team.clone(), tiebreaker(team, matches)))
+        .collect();
+    EventDistrictPoints { points, tiebreakers }
+}
+
+/// Get the teams that played for the winning alliance of a match, or none if it was tied or has
+/// not been played
+fn winning_teams(m: &Match) -> impl Iterator<Item = &TeamKey> {
+    let alliance = match m.winning_alliance {
+        MatchWinner::Red => Some(&m.alliances.red),
+        MatchWinner::Blue => Some(&m.alliances.blue),
+        _ => None,
+    };
+    alliance.into_iter().flat_map(|alliance| alliance.team_keys.iter())
+}
+
+/// Get the points earned by each team for the final placement of its alliance in a playoff
+/// bracket, awarded to every team that played at least one playoff match for the alliance
+fn placements(event: &Event, alliances: &[EliminationAlliance], playoffs: &[&Match]) -> HashMap<TeamKey, i32> {
+    let alliance_of = |teams: &[TeamKey]| alliances
+        .iter()
+        .position(|alliance| teams.iter().any(|team| alliance.picks.contains(team)
+            || alliance.backup.as_ref().and_then(|b| b.in_.as_ref()) == Some(team)
+        ));
+
+    let mut played = HashMap::<usize, HashSet<&TeamKey>>::new();
+    let mut finals_wins = HashMap::<usize, u32>::new();
+    let mut placed = Vec::<(usize, i32)>::new();
+    for m in playoffs {
+        let red = alliance_of(&m.alliances.red.team_keys);
+        let blue = alliance_of(&m.alliances.blue.team_keys);
+        for (alliance, teams) in [(red, &m.alliances.red.team_keys), (blue, &m.alliances.blue.team_keys)] {
+            if let Some(alliance) = alliance {
+                played.entry(alliance).or_default().extend(teams);
+            }
+        }
+
+        if m.comp_level == PlayoffLevel::F {
+            for alliance in [red, blue].into_iter().flatten() {
+                finals_wins.entry(alliance).or_default();
+            }
+        }
+
+        let (winner, loser) = match m.winning_alliance {
+            MatchWinner::Red => (red, blue),
+            MatchWinner::Blue => (blue, red),
+            _ => continue,
+        };
+        match (&m.comp_level, m.set_number) {
+            (PlayoffLevel::F, _) => if let Some(winner) = winner {
+                *finals_wins.entry(winner).or_default() += 1;
+            },
+            // The losers of the last two lower bracket matches of a double elimination bracket
+            // finish 4th and 3rd
+            (PlayoffLevel::SF, set @ (12 | 13)) if event.playoff_type == Some(PlayoffType::ModernDoubleElim8Team) => {
+                if let Some(loser) = loser {
+                    placed.push((loser, if set == 13 { 13 } else { 7 }));
+                }
+            },
+            _ => (),
+        }
+    }
+
+    if let Some((&winner, _)) = finals_wins.iter().find(|(_, wins)| **wins >= 2) {
+        placed.push((winner, 30));
+        let finalist = finals_wins.keys().copied().find(|alliance| *alliance != winner);
+        placed.extend(finalist.map(|finalist| (finalist, 20)));
+    }
+
+    placed
+        .into_iter()
+        .flat_map(|(alliance, points)| played
+            .get(&alliance)
+            .into_iter()
+            .flatten()
+            .map(move |team| ((*team).clone(), points))
+        )
+        .collect()
+}
+
+/// Get the highest qualification scores and qualification wins of a team, which break ties in
+/// district rankings
+fn tiebreaker(team: &TeamKey, matches: &[Match]) -> EventDistrictPointsTiebreaker {
+    let mut highest_qual_scores = Vec::new();
+    let mut qual_wins = 0;
+    for m in matches.iter().filter(|m| m.comp_level == PlayoffLevel::QM && m.is_played()) {
+        let (alliance, color) = match (&m.alliances.red, &m.alliances.blue) {
+            (red, _) if red.team_keys.contains(team) && !red.surrogate_team_keys.contains(team) => (red, MatchWinner::Red),
+            (_, blue) if blue.team_keys.contains(team) && !blue.surrogate_team_keys.contains(team) => (blue, MatchWinner::Blue),
+            _ => continue,
+        };
+        highest_qual_scores.extend(alliance.score);
+        if m.winning_alliance == color {
+            qual_wins += 1;
+        }
+    }
+
+    highest_qual_scores.sort_unstable_by(|a, b| b.cmp(a));
+    highest_qual_scores.truncate(3);
+    EventDistrictPointsTiebreaker { highest_qual_scores, qual_wins }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::{event::TeamEventStatusAllianceBackup, matches::test_util::{match_with, team}};
+
+    use super::*;
+
+    #[test]
+    fn qual_points_range() {
+        assert_eq!(qual_points(1, 40), 22);
+        assert_eq!(qual_points(40, 40), 4);
+        assert!((1..40).all(|rank| qual_points(rank, 40) >= qual_points(rank + 1, 40)));
+    }
+
+    #[test]
+    fn district_cmp_points() {
+        let mut event: Event = serde_json::from_str(include_str!("../../fixtures/event_2023cafr.json")).unwrap();
+        event.simple.event_type = EventType::DistrictCmp;
+        event.playoff_type = Some(PlayoffType::ModernDoubleElim8Team);
+        let rankings: EventRankings = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_rankings.json")).unwrap();
+        let awards: Vec<Award> = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_awards.json")).unwrap();
+
+        let alliance = |picks: [u32; 3]| EliminationAlliance {
+            name: None,
+            backup: None,
+            declined: Vec::new(),
+            picks: picks.map(team).to_vec(),
+            status: None,
+        };
+        let mut alliances = vec![alliance([1678, 5430, 8033]), alliance([254, 1280, 604])];
+        alliances[1].backup = Some(TeamEventStatusAllianceBackup { out: Some(team(604)), in_: Some(team(971)) });
+
+        let (red, blue) = ([1678, 5430, 8033].map(team), [254, 1280, 971].map(team));
+        let finals = [(1, (61, 47)), (2, (47, 61)), (3, (61, 47))]
+            .map(|(number, scores)| match_with(&format!("2023cafr_f1m{}", number), &red, &blue, Some(scores)));
+
+        let points = calculate(&event, Some(&rankings), &alliances, &finals, &awards);
+        let winner = &points.points[&team(1678)];
+        assert_eq!(winner.alliance_points, 16 * 3);
+        assert_eq!(winner.elim_points, 30 * 3);
+        let backup = &points.points[&team(971)];
+        assert_eq!((backup.alliance_points, backup.elim_points), (0, 20 * 3));
+        let replaced = &points.points[&team(604)];
+        assert_eq!((replaced.alliance_points, replaced.elim_points), (2 * 3, 0));
+        assert_eq!(points.points[&team(1280)].award_points, 0);
+        assert!(points.points.values().all(|p| p.total == p.qual_points + p.alliance_points + p.elim_points + p.award_points));
+    }
+
+    /// Compare the calculator with the points TBA published for a finished district event. Each
+    /// file in `fixtures/recorded` holds the response of the endpoint of the same name under
+    /// `/event/{event_key}`, with `event.json` holding that of `/event/{event_key}` itself
+    #[test]
+    #[ignore = "needs the API responses of a district event recorded into fixtures/recorded, see its README"]
+    fn recorded_event() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/recorded");
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        let event: Event = serde_json::from_str(&read("event.json")).unwrap();
+        let rankings: EventRankings = serde_json::from_str(&read("rankings.json")).unwrap();
+        let alliances: Vec<EliminationAlliance> = serde_json::from_str(&read("alliances.json")).unwrap();
+        let matches: Vec<Match> = serde_json::from_str(&read("matches.json")).unwrap();
+        let awards: Vec<Award> = serde_json::from_str(&read("awards.json")).unwrap();
+        let expected: EventDistrictPoints = serde_json::from_str(&read("district_points.json")).unwrap();
+
+        let points = calculate(&event, Some(&rankings), &alliances, &matches, &awards);
+        for (team, expected) in expected.points.iter() {
+            assert_eq!(points.points.get(team), Some(expected), "{}", team);
+        }
+        assert_eq!(points.points.len(), expected.points.len());
+    }
+}
@@ -0,0 +1,247 @@
+//! Elo ratings of teams, updated by replaying the results of matches in the order they were
+//! played
+
+use std::collections::HashMap;
+
+use crate::{
+    ctx::{endpoints::EndPoint, Context},
+    model::{
+        event::{EventType, PlayoffLevel},
+        matches::{Match, MatchKey, MatchWinner},
+        team::TeamKey,
+        Year,
+    },
+    Error,
+};
+
+/// Options controlling how ratings are updated after each match
+#[derive(Clone, Debug)]
+pub struct EloOptions {
+    /// Rating given to a team the first time it plays
+    pub initial: f64,
+    /// Largest change in rating for each team after a qualification match
+    pub k: f64,
+    /// Largest change in rating for each team after a playoff match
+    pub playoff_k: f64,
+    /// Fraction of the distance to the initial rating that each team's rating moves at the start
+    /// of a new season
+    pub mean_reversion: f64,
+    /// If set, the outcome of a match is scaled by its score margin as `0.5 + 0.5 * tanh(margin /
+    /// margin_scale)`, so that a win by this many points counts as about 0.88 of a full win.
+    /// Otherwise only the winner is counted
+    pub margin_scale: Option<f64>,
+    /// Include offseason and preseason events when replaying whole seasons
+    pub include_offseason: bool,
+}
+
+impl Default for EloOptions {
+    fn default() -> Self {
+        Self {
+            initial: 1500.,
+            k: 12.,
+            playoff_k: 3.,
+            mean_reversion: 0.2,
+            margin_scale: None,
+            include_offseason: false,
+        }
+    }
+}
+
+/// A team's rating before and after playing a match
+#[derive(Clone, Debug, PartialEq)]
+pub struct RatingChange {
+    pub match_key: MatchKey,
+    pub before: f64,
+    pub after: f64,
+    /// Probability that the team's alliance would win, predicted before the match was played
+    pub win_probability: f64,
+}
+
+/// Ratings of every team that has played in the matches replayed so far
+#[derive(Clone, Debug)]
+pub struct Elo {
+    options: EloOptions,
+    ratings: HashMap<TeamKey, f64>,
+    history: HashMap<TeamKey, Vec<RatingChange>>,
+    season: Option<Year>,
+}
+
+impl Elo {
+    /// Create a new rating engine in which no matches have been played
+    pub fn new(options: EloOptions) -> Self {
+        Self { options, ratings: HashMap::new(), history: HashMap::new(), season: None }
+    }
+
+    /// Get the current rating of a team, which is the initial rating if it has not played yet
+    pub fn rating(&self, team: &TeamKey) -> f64 {
+        self.ratings.get(team).copied().unwrap_or(self.options.initial)
+    }
+
+    /// Iterate over the current rating of every team that has played
+    pub fn ratings(&self) -> impl Iterator<Item = (&TeamKey, f64)> {
+        self.ratings.iter().map(|(team, rating)| (team, *rating))
+    }
+
+    /// Get the change in a team's rating after each match it has played, in play order
+    pub fn history(&self, team: &TeamKey) -> &[RatingChange] {
+        self.history.get(team).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get the probability that an alliance of the `red` teams beats an alliance of the `blue`
+    /// teams, using the sum of each alliance's ratings
+    pub fn red_win_probability(&self, red: &[TeamKey], blue: &[TeamKey]) -> f64 {
+        let sum = |teams: &[TeamKey]| teams.iter().map(|team| self.rating(team)).sum::<f64>();
+        Self::expected(sum(red), sum(blue))
+    }
+
+    /// Get the expected outcome for an alliance rated `red` against an alliance rated `blue`,
+    /// which is its probability of winning
+    pub(super) fn expected(red: f64, blue: f64) -> f64 {
+        1. / (1. + 10f64.powf((blue - red) / 400.))
+    }
+
+    /// Update the ratings of the teams in a match from its result. Matches must be given in the
+    /// order they were played, and matches that have not been played are ignored
+    pub fn update(&mut self, m: &Match) {
+        if !m.is_played() {
+            return
+        }
+        self.advance_to(m.event_key.year());
+
+        let expected = self.red_win_probability(&m.alliances.red.team_keys, &m.alliances.blue.team_keys);
+        let (red, blue) = (m.alliances.red.score.unwrap_or(0), m.alliances.blue.score.unwrap_or(0));
+        let actual = match self.options.margin_scale {
+            Some(scale) => 0.5 + 0.5 * ((red - blue) as f64 / scale).tanh(),
+            None => match m.winning_alliance {
+                MatchWinner::Red => 1.,
+                MatchWinner::Blue => 0.,
+                _ => 0.5,
+            },
+        };
+        let k = match m.comp_level {
+            PlayoffLevel::QM => self.options.k,
+            _ => self.options.playoff_k,
+        };
+        let delta = k * (actual - expected);
+
+        let alliances = [
+            (&m.alliances.red.team_keys, delta, expected),
+            (&m.alliances.blue.team_keys, -delta, 1. - expected),
+        ];
+        for (teams, delta, win_probability) in alliances {
+            for team in teams {
+                let before = self.rating(team);
+                self.ratings.insert(team.clone(), before + delta);
+                self.history.entry(team.clone()).or_default().push(RatingChange {
+                    match_key: m.key.clone(),
+                    before,
+                    after: before + delta,
+                    win_probability,
+                });
+            }
+        }
+    }
+
+    /// Update ratings from the given matches, sorted into the order they were played
+    pub fn replay<'a>(&mut self, matches: impl IntoIterator<Item = &'a Match>) {
+        let mut matches = matches.into_iter().collect::<Vec<_>>();
+        super::sort_chronologically(&mut matches);
+        for m in matches {
+            self.update(m);
+        }
+    }
+
+    /// Update ratings from every match of the events in the given seasons, which should be given
+    /// in order
+    pub async fn replay_years(&mut self, years: impl IntoIterator<Item = Year>, ctx: &Context) -> Result<(), Error> {
+        for year in years {
+            self.advance_to(year);
+            let events = ctx.endpoints.events.by_year.get((year,), ctx).await?;
+            let mut matches = Vec::new();
+            for event in events.iter() {
+                let offseason = matches!(event.simple.event_type, EventType::Offseason | EventType::Preseason);
+                if offseason && !self.options.include_offseason {
+                    continue
+                }
+                matches.push(ctx.endpoints.event.matches.get((event.simple.key.clone(),), ctx).await?);
+            }
+            self.replay(matches.iter().flat_map(|matches| matches.iter()));
+        }
+        Ok(())
+    }
+
+    /// Start the season of `year` if it is later than the current season, moving every rating
+    /// towards the initial rating. [Elo::update] and [Elo::replay_years] call this, so it only
+    /// needs to be called to read ratings for a season before any of its matches are played
+    pub fn advance_to(&mut self, year: Year) {
+        if self.season.is_some_and(|season| season >= year) {
+            return
+        }
+        if self.season.is_some() {
+            for rating in self.ratings.values_mut() {
+                *rating += (self.options.initial - *rating) * self.options.mean_reversion;
+            }
+        }
+        self.season = Some(year);
+    }
+}
+
+impl Default for Elo {
+    fn default() -> Self {
+        Self::new(EloOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::matches::test_util::{fixture, match_with};
+
+    use super::*;
+
+    #[test]
+    fn replay_seasons() {
+        let template = fixture();
+        let mut elo = Elo::default();
+        let (red, blue) = (template.alliances.red.team_keys.clone(), template.alliances.blue.team_keys.clone());
+        assert_eq!(elo.red_win_probability(&red, &blue), 0.5);
+
+        elo.replay([&template, &template]);
+        let winner = &blue[0];
+        assert_eq!(elo.history(winner).len(), 2);
+        assert_eq!(elo.history(winner)[0].win_probability, 0.5);
+        assert!(elo.history(winner)[1].win_probability > 0.5);
+        assert!(elo.history(&red[0])[1].win_probability < 0.5);
+        assert!(elo.rating(winner) > 1500.);
+        assert!(elo.rating(&red[0]) < 1500.);
+        assert!(elo.red_win_probability(&red, &blue) < 0.5);
+        assert!((elo.red_win_probability(&red, &blue) + elo.red_win_probability(&blue, &red) - 1.).abs() < 1e-12);
+
+        let before = elo.rating(winner);
+        elo.update(&match_with("2024cafr_qm1", &red, &blue, Some((50, 50))));
+        let reverted = before + (1500. - before) * 0.2;
+        assert_eq!(elo.history(winner).last().unwrap().before, reverted);
+    }
+
+    #[test]
+    fn advance_seasons() {
+        let template = fixture();
+        let mut elo = Elo::default();
+        elo.update(&template);
+        let winner = &template.alliances.blue.team_keys[0];
+        let before = elo.rating(winner);
+
+        // Ratings for the next season are reverted before any of its matches are played
+        elo.advance_to(Year::new(2024).unwrap());
+        let reverted = before + (1500. - before) * 0.2;
+        assert_eq!(elo.rating(winner), reverted);
+
+        // Advancing to the same or an earlier season does nothing
+        elo.advance_to(Year::new(2024).unwrap());
+        elo.advance_to(Year::new(2023).unwrap());
+        assert_eq!(elo.rating(winner), reverted);
+
+        // Nor does playing a match of the season already started
+        elo.update(&match_with("2024cafr_qm1", &template.alliances.red.team_keys, &template.alliances.blue.team_keys, Some((47, 61))));
+        assert_eq!(elo.history(winner).last().unwrap().before, reverted);
+    }
+}
@@ -0,0 +1,164 @@
+//! History of the matches two teams have played together, as partners or as opponents
+
+use futures_util::future::try_join_all;
+
+use crate::{
+    ctx::{endpoints::EndPoint, Context},
+    model::{
+        matches::{AllianceColor, Match, MatchWinner},
+        team::TeamKey,
+        Year,
+    },
+    Error,
+};
+
+/// Whether two teams played on the same alliance or on opposing alliances
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    Partner,
+    Opponent,
+}
+
+/// A played match that both teams appeared in
+#[derive(Clone, Debug)]
+pub struct SharedMatch {
+    pub match_: Match,
+    pub relation: Relation,
+    /// Alliance color of the first team
+    pub color: AllianceColor,
+    pub winner: MatchWinner,
+    /// Score of the first team's alliance minus the score of the other alliance
+    pub margin: i32,
+}
+
+/// Wins, losses and ties over a set of matches
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+}
+
+impl Record {
+    /// Get the number of matches in this record
+    pub fn matches(&self) -> u32 {
+        self.wins + self.losses + self.ties
+    }
+
+    /// Get the fraction of matches won, counting ties as half a win, or `None` if there are no
+    /// matches
+    pub fn win_rate(&self) -> Option<f64> {
+        let matches = self.matches();
+        (matches > 0).then(|| (f64::from(self.wins) + 0.5 * f64::from(self.ties)) / f64::from(matches))
+    }
+
+    fn add(&mut self, won: Option<bool>) {
+        match won {
+            Some(true) => self.wins += 1,
+            Some(false) => self.losses += 1,
+            None => self.ties += 1,
+        }
+    }
+}
+
+/// Every played match shared by two teams, with their combined record as partners and the
+/// first team's record against the second. Matches whose winner is not recognized are listed
+/// but left out of both records
+#[derive(Clone, Debug)]
+pub struct HeadToHead {
+    pub first: TeamKey,
+    pub second: TeamKey,
+    /// Shared matches in the order they were played
+    pub matches: Vec<SharedMatch>,
+    pub as_partners: Record,
+    pub against: Record,
+}
+
+impl HeadToHead {
+    /// Find the played matches shared by two teams among the given matches, ignoring matches
+    /// either team played as a surrogate
+    pub fn new(first: TeamKey, second: TeamKey, matches: &[Match]) -> Self {
+        let mut played = matches.iter().filter(|m| m.is_played()).collect::<Vec<_>>();
+        super::sort_chronologically(&mut played);
+
+        let (mut as_partners, mut against) = (Record::default(), Record::default());
+        let mut shared = Vec::new();
+        for m in played {
+            let color = |team: &TeamKey| [AllianceColor::Red, AllianceColor::Blue].into_iter().find(|color| {
+                let alliance = m.alliances.get(*color);
+                alliance.team_keys.contains(team) && !alliance.surrogate_team_keys.contains(team)
+            });
+            let (Some(color), Some(other)) = (color(&first), color(&second)) else { continue };
+
+            let relation = if color == other { Relation::Partner } else { Relation::Opponent };
+            let (red, blue) = (m.alliances.red.score.unwrap_or(0), m.alliances.blue.score.unwrap_or(0));
+            let margin = if color == AllianceColor::Red { red - blue } else { blue - red };
+            let won = match &m.winning_alliance {
+                MatchWinner::Red | MatchWinner::Blue => Some(Some(m.winning_alliance == color.into())),
+                MatchWinner::Tie => Some(None),
+                MatchWinner::Unknown(_) => None,
+            };
+            match (relation, won) {
+                (_, None) => {}
+                (Relation::Partner, Some(won)) => as_partners.add(won),
+                (Relation::Opponent, Some(won)) => against.add(won),
+            }
+            shared.push(SharedMatch { match_: m.clone(), relation, color, winner: m.winning_alliance.clone(), margin });
+        }
+
+        Self { first, second, matches: shared, as_partners, against }
+    }
+
+    /// Iterate over the shared matches in which the teams had the given relation
+    pub fn with_relation(&self, relation: Relation) -> impl Iterator<Item = &SharedMatch> {
+        self.matches.iter().filter(move |shared| shared.relation == relation)
+    }
+
+    /// Fetch the matches of the first team in each of the given years, concurrently, and find
+    /// those shared with the second team
+    pub async fn fetch(first: TeamKey, second: TeamKey, years: impl IntoIterator<Item = Year>, ctx: &Context) -> Result<Self, Error> {
+        let matches = try_join_all(years
+            .into_iter()
+            .map(|year| ctx.endpoints.team.matches_by_year.get((first.clone(), year), ctx))
+        ).await?;
+        let matches = matches.iter().flat_map(|matches| matches.iter().cloned()).collect::<Vec<_>>();
+        Ok(Self::new(first, second, &matches))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::matches::test_util::{fixture, match_with};
+
+    use super::*;
+
+    #[test]
+    fn shared_matches() {
+        let m = fixture();
+        let (red, blue) = (&m.alliances.red.team_keys, &m.alliances.blue.team_keys);
+        let mut unknown = match_with("2023cafr_qm3", red, blue, Some((47, 61)));
+        unknown.winning_alliance = MatchWinner::Unknown("purple".to_owned());
+        let matches = [
+            match_with("2023cafr_qm1", red, blue, Some((47, 61))),
+            match_with("2023cafr_qm2", red, blue, Some((70, 61))),
+            match_with("2023cafr_qm4", red, blue, None),
+            unknown,
+        ];
+
+        let rivals = HeadToHead::new(red[0].clone(), blue[0].clone(), &matches);
+        assert_eq!(rivals.matches.len(), 3);
+        assert_eq!(rivals.matches[0].margin, 47 - 61);
+        assert_eq!(rivals.matches[1].margin, 70 - 61);
+        assert!(rivals.matches.iter().all(|shared| shared.relation == Relation::Opponent));
+        assert!(rivals.matches.iter().all(|shared| shared.color == AllianceColor::Red));
+        assert_eq!(rivals.against, Record { wins: 1, losses: 1, ties: 0 });
+        assert_eq!(rivals.against.win_rate(), Some(0.5));
+        assert_eq!(rivals.as_partners.win_rate(), None);
+
+        let partners = HeadToHead::new(blue[0].clone(), blue[1].clone(), &matches);
+        assert_eq!(partners.with_relation(Relation::Partner).count(), 3);
+        assert!(partners.matches.iter().all(|shared| shared.color == AllianceColor::Blue));
+        assert_eq!(partners.as_partners, Record { wins: 1, losses: 1, ties: 0 });
+        assert!(HeadToHead::new(red[0].clone(), "frc9999".parse().unwrap(), &matches).matches.is_empty());
+    }
+}
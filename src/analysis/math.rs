@@ -0,0 +1,210 @@
+use std::f64::consts::PI;
+
+/// Compute the error function of `x`
+pub(crate) fn erf(x: f64) -> f64 {
+    if x.is_nan() {
+        return x
+    }
+    if x.abs() > 6. {
+        return x.signum()
+    }
+
+    // erf(x) = 2/sqrt(pi) * exp(-x^2) * sum(2^n x^(2n+1) / (1 * 3 * ... * (2n+1))), which has
+    // only positive terms and so does not lose precision to cancellation
+    let mut term = x;
+    let mut sum = x;
+    for n in 1..500 {
+        term *= 2. * x * x / (2 * n + 1) as f64;
+        sum += term;
+        if term.abs() < sum.abs() * f64::EPSILON {
+            break
+        }
+    }
+    2. / PI.sqrt() * (-x * x).exp() * sum
+}
+
+/// Compute the inverse of the error function, returning infinity for `y` of -1 or 1 and NaN for
+/// values outside of that range
+pub(crate) fn erfinv(y: f64) -> f64 {
+    if y.is_nan() || y.abs() > 1. {
+        return f64::NAN
+    }
+    if y.abs() == 1. {
+        return y * f64::INFINITY
+    }
+
+    // erf is concave on either side of 0, so Newton's method started from 0 approaches the root
+    // monotonically without overshooting it
+    let mut x = 0f64;
+    for _ in 0..100 {
+        let step = (erf(x) - y) / (2. / PI.sqrt() * (-x * x).exp());
+        x -= step;
+        if step.abs() <= x.abs() * 1e-15 {
+            break
+        }
+    }
+    x
+}
+
+/// Solve the least-squares problem `min |Ax - b|^2 + ridge * |x|^2` through its normal equations,
+/// where each row of `A` is given by the column indices of its entries that are 1 and all other
+/// entries are 0. Returns `None` if the system is underdetermined
+pub(crate) fn least_squares(rows: &[(Vec<usize>, f64)], columns: usize, ridge: f64) -> Option<Vec<f64>> {
+    let mut ata = vec![vec![0f64; columns]; columns];
+    let mut atb = vec![0f64; columns];
+    for (cols, b) in rows {
+        for &i in cols {
+            atb[i] += b;
+            for &j in cols {
+                ata[i][j] += 1.;
+            }
+        }
+    }
+    for (i, row) in ata.iter_mut().enumerate() {
+        row[i] += ridge;
+    }
+
+    // Cholesky decomposition of the symmetric matrix A^T A into L L^T
+    let mut l = vec![vec![0f64; columns]; columns];
+    for i in 0..columns {
+        for j in 0..=i {
+            let sum = ata[i][j] - (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+            if i == j {
+                if sum <= 1e-9 {
+                    return None
+                }
+                l[i][i] = sum.sqrt();
+            } else {
+                l[i][j] = sum / l[j][j];
+            }
+        }
+    }
+
+    // Forward substitution for L y = A^T b, then back substitution for L^T x = y
+    let mut y = vec![0f64; columns];
+    for i in 0..columns {
+        y[i] = (atb[i] - (0..i).map(|k| l[i][k] * y[k]).sum::<f64>()) / l[i][i];
+    }
+    let mut x = vec![0f64; columns];
+    for i in (0..columns).rev() {
+        x[i] = (y[i] - (i + 1..columns).map(|k| l[k][i] * x[k]).sum::<f64>()) / l[i][i];
+    }
+    Some(x)
+}
+
+/// Solution of the least-squares problem `min |Ax - b|^2 + ridge * |x|^2` that is updated one
+/// row at a time, for rows of the same form as in [`least_squares`]. Keeps the inverse of
+/// `A^T A + ridge * I`, so that adding a row takes time proportional to the square of the number
+/// of columns rather than the cube
+#[derive(Clone, Debug)]
+pub(crate) struct RecursiveLeastSquares {
+    ridge: f64,
+    inverse: Vec<Vec<f64>>,
+    atb: Vec<f64>,
+}
+
+impl RecursiveLeastSquares {
+    /// Create a problem with no rows or columns. `ridge` must be positive for the inverse to exist
+    pub(crate) fn new(ridge: f64) -> Self {
+        Self { ridge, inverse: Vec::new(), atb: Vec::new() }
+    }
+
+    /// Add a column with no entries, returning its index
+    pub(crate) fn add_column(&mut self) -> usize {
+        let n = self.atb.len();
+        for row in self.inverse.iter_mut() {
+            row.push(0.);
+        }
+        let mut row = vec![0.; n + 1];
+        row[n] = 1. / self.ridge;
+        self.inverse.push(row);
+        self.atb.push(0.);
+        n
+    }
+
+    /// Add a row whose entries are 1 in the given columns and 0 elsewhere
+    pub(crate) fn add_row(&mut self, cols: &[usize], b: f64) {
+        // Sherman-Morrison: (M + a a^T)^-1 = M^-1 - (M^-1 a)(M^-1 a)^T / (1 + a^T M^-1 a), as M is
+        // symmetric
+        let pa = self.inverse
+            .iter()
+            .map(|row| cols.iter().map(|&j| row[j]).sum::<f64>())
+            .collect::<Vec<_>>();
+        let denominator = 1. + cols.iter().map(|&j| pa[j]).sum::<f64>();
+        for (row, pi) in self.inverse.iter_mut().zip(&pa) {
+            for (entry, pj) in row.iter_mut().zip(&pa) {
+                *entry -= pi * pj / denominator;
+            }
+        }
+        for &i in cols {
+            self.atb[i] += b;
+        }
+    }
+
+    /// Get the entry of the solution for a column
+    pub(crate) fn solution(&self, column: usize) -> f64 {
+        self.inverse[column].iter().zip(&self.atb).map(|(p, b)| p * b).sum()
+    }
+}
+
+/// Small pseudorandom number generator (xorshift64*) used for simulations, so that results can
+/// be reproduced from a seed
+#[derive(Clone, Debug)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // The state must never be 0, so mix the seed into an odd constant
+        Self(seed ^ 0x9E37_79B9_7F4A_7C15 | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Get a uniformly distributed number in `[0, 1)`
+    pub(crate) fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Get a normally distributed number with the given mean and standard deviation, using the
+    /// Box-Muller transform
+    pub(crate) fn normal(&mut self, mean: f64, stdev: f64) -> f64 {
+        let u = 1. - self.uniform();
+        let v = self.uniform();
+        mean + stdev * (-2. * u.ln()).sqrt() * (2. * PI * v).cos()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn erf_inverse() {
+        assert!((erf(0.5) - 0.520_499_877_813_046_5).abs() < 1e-15);
+        assert!((erf(-2.) + 0.995_322_265_018_952_7).abs() < 1e-15);
+        for y in [-0.99, -0.5, 0., 0.1, 0.9346] {
+            assert!((erf(erfinv(y)) - y).abs() < 1e-14);
+        }
+    }
+
+    #[test]
+    fn recursive_least_squares() {
+        let rows = [(vec![0, 1], 5.), (vec![1, 2], 7.), (vec![0, 2], 6.), (vec![0, 1, 2], 9.5), (vec![2], 4.)];
+        let mut solver = RecursiveLeastSquares::new(0.5);
+        for _ in 0..3 {
+            solver.add_column();
+        }
+        for (cols, b) in rows.iter() {
+            solver.add_row(cols, *b);
+        }
+        let expected = least_squares(&rows, 3, 0.5).unwrap();
+        for (i, expected) in expected.into_iter().enumerate() {
+            assert!((solver.solution(i) - expected).abs() < 1e-9);
+        }
+    }
+}
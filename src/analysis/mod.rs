@@ -0,0 +1,22 @@
+//! Statistics and projections computed locally from data returned by the API
+
+use crate::model::matches::Match;
+
+mod math;
+pub mod district_points;
+pub mod opr;
+pub mod elo;
+pub mod predict;
+pub mod rank_sim;
+pub mod sos;
+pub mod alliance_selection;
+pub mod head_to_head;
+
+/// Sort matches into the order they were played across events and seasons, using their start
+/// times where known and their keys within an event
+pub(crate) fn sort_chronologically(matches: &mut [&Match]) {
+    matches.sort_by(|a, b| a.event_key.year().cmp(&b.event_key.year())
+        .then(a.actual_time.or(a.time).cmp(&b.actual_time.or(b.time)))
+        .then_with(|| a.cmp_play_order(b))
+    );
+}
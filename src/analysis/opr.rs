@@ -0,0 +1,181 @@
+//! Least-squares estimates of each team's contribution to its alliance's score, computed from
+//! the matches of an event
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+use crate::model::{
+    event::{EventOPRs, PlayoffLevel},
+    matches::{Match, MatchAlliance},
+    team::TeamKey,
+};
+
+use super::math::least_squares;
+
+/// Options controlling which matches and teams are used to compute OPRs
+#[derive(Clone, Debug)]
+pub struct OprOptions {
+    /// Include playoff matches as well as qualification matches
+    pub include_playoffs: bool,
+    /// Count the contribution of teams playing a match as a surrogate. TBA's published OPRs
+    /// leave surrogate appearances out
+    pub include_surrogates: bool,
+    /// Count the contribution of teams that were disqualified from a match
+    pub include_dqs: bool,
+    /// Amount of ridge regularization, which pulls every team's rating towards 0 and allows a
+    /// solution before every team has played enough matches
+    pub ridge: f64,
+}
+
+impl Default for OprOptions {
+    fn default() -> Self {
+        Self {
+            include_playoffs: false,
+            include_surrogates: false,
+            include_dqs: true,
+            ridge: 0.,
+        }
+    }
+}
+
+/// Errors that can occur while solving for OPRs
+#[derive(Debug, thiserror::Error)]
+pub enum OprError {
+    #[error("Not enough matches have been played to solve for every team's rating")]
+    Underdetermined,
+}
+
+/// Compute the OPR, DPR and CCWM of every team that played in the given matches
+pub fn oprs(matches: &[Match], options: &OprOptions) -> Result<EventOPRs, OprError> {
+    let oprs = solve(matches, options, |_, _, alliance, _| alliance.score.map(f64::from))?;
+    let dprs = solve(matches, options, |_, _, _, opponent| opponent.score.map(f64::from))?;
+    let ccwms = oprs
+        .iter()
+        .map(|(team, opr)| (team.clone(), opr - dprs[team]))
+        .collect();
+    Ok(EventOPRs { oprs, dprs, ccwms })
+}
+
+/// Compute every team's contribution to a numeric field of the score breakdown, e.g.
+/// `autoPoints`. Boolean fields are counted as 0 or 1, so that e.g. the contribution to a bonus
+/// is the rate at which a team helps earn it. Matches without the field are skipped
+pub fn component_oprs(matches: &[Match], field: &str, options: &OprOptions) -> Result<HashMap<TeamKey, f64>, OprError> {
+    solve(matches, options, |m, color, _, _| {
+        match m.score_breakdown.as_ref()?.get(color)?.get(field)? {
+            Value::Bool(b) => Some(f64::from(u8::from(*b))),
+            val => val.as_f64(),
+        }
+    })
+}
+
+/// Teams of an alliance whose contribution is counted under the given options
+pub(super) fn contributors<'a>(alliance: &'a MatchAlliance, options: &'a OprOptions) -> impl Iterator<Item = &'a TeamKey> {
+    alliance
+        .team_keys
+        .iter()
+        .filter(|team| options.include_surrogates || !alliance.surrogate_team_keys.contains(team))
+        .filter(|team| options.include_dqs || !alliance.dq_team_keys.contains(team))
+}
+
+/// Solve for each team's contribution to a value of each alliance in each played match, given
+/// the match, the alliance's color, the alliance, and the opposing alliance
+fn solve(
+    matches: &[Match],
+    options: &OprOptions,
+    value: impl Fn(&Match, &str, &MatchAlliance, &MatchAlliance) -> Option<f64>,
+) -> Result<HashMap<TeamKey, f64>, OprError> {
+    let matches = matches
+        .iter()
+        .filter(|m| options.include_playoffs || m.comp_level == PlayoffLevel::QM)
+        .filter(|m| m.is_played());
+
+    let mut rows = Vec::new();
+    for m in matches {
+        let (red, blue) = (&m.alliances.red, &m.alliances.blue);
+        for (color, alliance, opponent) in [("red", red, blue), ("blue", blue, red)] {
+            if let Some(value) = value(m, color, alliance, opponent) {
+                rows.push((contributors(alliance, options).cloned().collect::<Vec<_>>(), value));
+            }
+        }
+    }
+
+    let teams = rows
+        .iter()
+        .flat_map(|(teams, _)| teams.iter().cloned())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let index = teams
+        .iter()
+        .enumerate()
+        .map(|(i, team)| (team.clone(), i))
+        .collect::<HashMap<_, _>>();
+    let rows = rows
+        .into_iter()
+        .map(|(teams, value)| (teams.iter().map(|team| index[team]).collect(), value))
+        .collect::<Vec<_>>();
+
+    let solution = least_squares(&rows, teams.len(), options.ridge).ok_or(OprError::Underdetermined)?;
+    Ok(teams.into_iter().zip(solution).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::matches::test_util::{match_with, teams};
+
+    use super::*;
+
+    #[test]
+    fn recover_contributions() {
+        let teams = teams(1..=8);
+        let contribution = |i: usize| (i as f64 + 1.) * 3.;
+        let schedule = [
+            ([0, 1, 2], [3, 4, 5]), ([6, 7, 0], [1, 3, 5]), ([2, 4, 6], [7, 1, 5]), ([3, 6, 1], [0, 2, 7]),
+            ([4, 5, 7], [0, 6, 3]), ([1, 2, 5], [4, 6, 0]), ([7, 3, 2], [5, 6, 4]), ([0, 4, 7], [2, 3, 6]),
+        ];
+        let matches = schedule.iter().enumerate().map(|(n, (red, blue))| {
+            let keys = |picks: &[usize; 3]| picks.map(|i| teams[i].clone());
+            let score = |picks: &[usize; 3]| picks.iter().map(|i| contribution(*i)).sum::<f64>() as i32;
+            let mut m = match_with(&format!("2023cafr_qm{}", n + 1), &keys(red), &keys(blue), Some((score(red), score(blue))));
+            for color in ["red", "blue"] {
+                m.score_breakdown.as_mut().unwrap()[color]["autoPoints"] = 6.into();
+            }
+            m
+        }).collect::<Vec<_>>();
+
+        let result = oprs(&matches, &OprOptions::default()).unwrap();
+        for (i, team) in teams.iter().enumerate() {
+            assert!((result.oprs[team] - contribution(i)).abs() < 1e-6);
+            assert!((result.ccwms[team] - (result.oprs[team] - result.dprs[team])).abs() < 1e-9);
+        }
+        let auto = component_oprs(&matches, "autoPoints", &OprOptions::default()).unwrap();
+        assert!(auto.values().all(|opr| (opr - 2.).abs() < 1e-6));
+
+        assert!(matches!(oprs(&matches[..2], &OprOptions::default()), Err(OprError::Underdetermined)));
+        assert!(oprs(&matches[..2], &OprOptions { ridge: 1., ..Default::default() }).is_ok());
+    }
+
+    /// Compare the solver with the OPRs TBA published for an event whose
+    /// `/event/{event_key}/matches` and `/event/{event_key}/oprs` responses are recorded into
+    /// `fixtures/recorded` as `matches.json` and `oprs.json`
+    #[test]
+    #[ignore = "needs the API responses of an event recorded into fixtures/recorded, see its README"]
+    fn recorded_event() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/recorded");
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        let matches: Vec<Match> = serde_json::from_str(&read("matches.json")).unwrap();
+        let expected: EventOPRs = serde_json::from_str(&read("oprs.json")).unwrap();
+
+        let result = oprs(&matches, &OprOptions::default()).unwrap();
+        for (name, result, expected) in [
+            ("OPR", &result.oprs, &expected.oprs),
+            ("DPR", &result.dprs, &expected.dprs),
+            ("CCWM", &result.ccwms, &expected.ccwms),
+        ] {
+            for (team, expected) in expected.iter() {
+                assert!((result[team] - expected).abs() < 1e-3, "{} of {}: {} != {}", name, team, result[team], expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Predictions of match outcomes, and backtesting of predictors against the results of past
+//! matches
+
+use std::collections::HashMap;
+
+use crate::model::{event::PlayoffLevel, matches::{Match, MatchWinner}, team::TeamKey};
+
+use super::{elo::Elo, math::{erf, RecursiveLeastSquares}, opr::{contributors, OprOptions}};
+
+/// Predicted outcome of a match
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Prediction {
+    /// Predicted score of the red alliance, if the predictor models scores
+    pub red_score: Option<f64>,
+    /// Predicted score of the blue alliance, if the predictor models scores
+    pub blue_score: Option<f64>,
+    /// Probability that the red alliance wins
+    pub red_win_probability: f64,
+}
+
+/// Trait implemented by models that predict the outcome of matches from the results of matches
+/// played before them
+pub trait Predictor {
+    /// Predict the outcome of a match between an alliance of the `red` teams and an alliance of
+    /// the `blue` teams
+    fn predict_teams(&self, red: &[TeamKey], blue: &[TeamKey]) -> Prediction;
+
+    /// Update this model with the result of a played match
+    fn observe(&mut self, m: &Match);
+
+    /// Predict the outcome of a match from the teams on each alliance
+    fn predict(&self, m: &Match) -> Prediction {
+        self.predict_teams(&m.alliances.red.team_keys, &m.alliances.blue.team_keys)
+    }
+}
+
+impl Predictor for Elo {
+    fn predict_teams(&self, red: &[TeamKey], blue: &[TeamKey]) -> Prediction {
+        Prediction {
+            red_score: None,
+            blue_score: None,
+            red_win_probability: self.red_win_probability(red, blue),
+        }
+    }
+
+    fn observe(&mut self, m: &Match) {
+        self.update(m)
+    }
+}
+
+/// Predicts scores as the sum of the OPRs of each alliance, computed from the matches observed
+/// so far, and the win probability from how widely match margins are spread around the
+/// predicted margin.
+///
+/// OPRs are updated with each match rather than solved again from every match observed, so
+/// observing a match takes time proportional to the square of the number of teams seen. This
+/// needs some regularization, so a `ridge` below [`OprPredictor::MIN_RIDGE`] is raised to it
+#[derive(Clone, Debug)]
+pub struct OprPredictor {
+    options: OprOptions,
+    solver: RecursiveLeastSquares,
+    /// Column of each team in `solver`
+    teams: HashMap<TeamKey, usize>,
+    observed: usize,
+    /// Total score and number of teams of every alliance observed
+    points: f64,
+    appearances: f64,
+    /// Root mean square difference between the predicted and actual margins of observed matches
+    margin_error: f64,
+}
+
+impl OprPredictor {
+    /// Smallest amount of ridge regularization used to compute OPRs
+    pub const MIN_RIDGE: f64 = 1e-3;
+
+    /// Create a predictor that computes OPRs with the given options and has observed no matches
+    pub fn new(options: OprOptions) -> Self {
+        Self {
+            solver: RecursiveLeastSquares::new(options.ridge.max(Self::MIN_RIDGE)),
+            options,
+            teams: HashMap::new(),
+            observed: 0,
+            points: 0.,
+            appearances: 0.,
+            margin_error: 0.,
+        }
+    }
+
+    /// Get a team's OPR from the matches observed so far, if it has played in any of them
+    pub fn opr(&self, team: &TeamKey) -> Option<f64> {
+        self.teams.get(team).map(|&column| self.solver.solution(column))
+    }
+
+    fn alliance_score(&self, teams: &[TeamKey]) -> f64 {
+        // Teams without an OPR are assumed to contribute the average score per team
+        let default_opr = self.points / self.appearances.max(1.);
+        teams
+            .iter()
+            .map(|team| self.opr(team).unwrap_or(default_opr))
+            .sum()
+    }
+}
+
+impl Default for OprPredictor {
+    /// Create a predictor that includes playoff matches, with a small amount of regularization so
+    /// that OPRs are available from the first match
+    fn default() -> Self {
+        Self::new(OprOptions { include_playoffs: true, ridge: 0.1, ..Default::default() })
+    }
+}
+
+impl Predictor for OprPredictor {
+    fn predict_teams(&self, red: &[TeamKey], blue: &[TeamKey]) -> Prediction {
+        let (red_score, blue_score) = (self.alliance_score(red), self.alliance_score(blue));
+        let margin = red_score - blue_score;
+        let red_win_probability = match self.margin_error > 0. {
+            true => 0.5 * (1. + erf(margin / (self.margin_error * std::f64::consts::SQRT_2))),
+            false if margin > 0. => 1.,
+            false if margin < 0. => 0.,
+            false => 0.5,
+        };
+        Prediction { red_score: Some(red_score), blue_score: Some(blue_score), red_win_probability }
+    }
+
+    fn observe(&mut self, m: &Match) {
+        if !m.is_played() {
+            return
+        }
+        let predicted = self.predict(m);
+        let error = (m.alliances.red.score.unwrap_or(0) - m.alliances.blue.score.unwrap_or(0)) as f64
+            - (predicted.red_score.unwrap_or(0.) - predicted.blue_score.unwrap_or(0.));
+        let n = self.observed as f64;
+        self.margin_error = ((self.margin_error.powi(2) * n + error.powi(2)) / (n + 1.)).sqrt();
+        self.observed += 1;
+
+        let counted = self.options.include_playoffs || m.comp_level == PlayoffLevel::QM;
+        for alliance in [&m.alliances.red, &m.alliances.blue] {
+            let score = f64::from(alliance.score.unwrap_or(0));
+            self.points += score;
+            self.appearances += alliance.team_keys.len() as f64;
+            if !counted || alliance.score.is_none() {
+                continue
+            }
+            let columns = contributors(alliance, &self.options)
+                .map(|team| match self.teams.get(team) {
+                    Some(&column) => column,
+                    None => {
+                        let column = self.solver.add_column();
+                        self.teams.insert(team.clone(), column);
+                        column
+                    }
+                })
+                .collect::<Vec<_>>();
+            self.solver.add_row(&columns, score);
+        }
+    }
+}
+
+/// Combines the predictions of two predictors, weighting the first by `weight` and the second
+/// by `1 - weight`. Scores are taken from whichever predictors model them
+#[derive(Clone, Debug)]
+pub struct Blend<A, B> {
+    pub first: A,
+    pub second: B,
+    pub weight: f64,
+}
+
+impl<A: Predictor, B: Predictor> Predictor for Blend<A, B> {
+    fn predict_teams(&self, red: &[TeamKey], blue: &[TeamKey]) -> Prediction {
+        let (a, b) = (self.first.predict_teams(red, blue), self.second.predict_teams(red, blue));
+        let blend = |a: Option<f64>, b: Option<f64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a * self.weight + b * (1. - self.weight)),
+            (a, b) => a.or(b),
+        };
+        Prediction {
+            red_score: blend(a.red_score, b.red_score),
+            blue_score: blend(a.blue_score, b.blue_score),
+            red_win_probability: a.red_win_probability * self.weight + b.red_win_probability * (1. - self.weight),
+        }
+    }
+
+    fn observe(&mut self, m: &Match) {
+        self.first.observe(m);
+        self.second.observe(m);
+    }
+}
+
+/// Matches whose predicted red win probability fell within a range, and how often red won them
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalibrationBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    /// Average predicted red win probability of the matches in this bucket
+    pub mean_predicted: f64,
+    /// Fraction of the matches in this bucket won by red, counting ties as half a win
+    pub observed_rate: f64,
+}
+
+/// Accuracy of a predictor over a set of played matches
+#[derive(Clone, Debug, PartialEq)]
+pub struct BacktestReport {
+    /// Number of played matches predicted
+    pub matches: usize,
+    /// Mean squared difference between the predicted red win probability and the outcome
+    pub brier: f64,
+    /// Fraction of matches with a winner in which the favored alliance won
+    pub accuracy: f64,
+    /// Matches grouped into ten buckets of equal width by predicted red win probability
+    pub calibration: Vec<CalibrationBucket>,
+}
+
+/// Replay matches in the order they were played, predicting each one using only the matches
+/// before it and then observing its result
+pub fn backtest<P: Predictor + ?Sized>(predictor: &mut P, matches: &[Match]) -> BacktestReport {
+    const BUCKETS: usize = 10;
+
+    let mut matches = matches.iter().filter(|m| m.is_played()).collect::<Vec<_>>();
+    super::sort_chronologically(&mut matches);
+
+    let mut brier = 0.;
+    let (mut correct, mut decided) = (0usize, 0usize);
+    let mut buckets = vec![(0usize, 0f64, 0f64); BUCKETS];
+    for m in matches.iter() {
+        let p = predictor.predict(m).red_win_probability;
+        let outcome = match m.winning_alliance {
+            MatchWinner::Red => 1.,
+            MatchWinner::Blue => 0.,
+            _ => 0.5,
+        };
+        brier += (p - outcome).powi(2);
+        if outcome != 0.5 {
+            decided += 1;
+            correct += usize::from((p > 0.5) == (outcome == 1.) && p != 0.5);
+        }
+        let bucket = &mut buckets[((p * BUCKETS as f64) as usize).min(BUCKETS - 1)];
+        bucket.0 += 1;
+        bucket.1 += p;
+        bucket.2 += outcome;
+
+        predictor.observe(m);
+    }
+
+    let count = matches.len();
+    BacktestReport {
+        matches: count,
+        brier: brier / count.max(1) as f64,
+        accuracy: correct as f64 / decided.max(1) as f64,
+        calibration: buckets
+            .into_iter()
+            .enumerate()
+            .map(|(i, (count, predicted, observed))| CalibrationBucket {
+                lower: i as f64 / BUCKETS as f64,
+                upper: (i + 1) as f64 / BUCKETS as f64,
+                count,
+                mean_predicted: predicted / count.max(1) as f64,
+                observed_rate: observed / count.max(1) as f64,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{analysis::opr::oprs, model::matches::test_util::{match_with, teams}};
+
+    use super::*;
+
+    #[test]
+    fn backtest_predictors() {
+        let teams = teams(1..=6);
+        // Teams 1 to 3 are much stronger than teams 4 to 6, and the alliances are reshuffled each
+        // match so that red wins whenever it has more strong teams
+        let matches = (1..=30u32).map(|n| {
+            let red = [(n as usize) % 3, 3 + (n as usize + 1) % 3, if n % 2 == 0 { (n as usize + 1) % 3 } else { 3 + (n as usize) % 3 }];
+            let blue = (0..6).filter(|i| !red.contains(i)).collect::<Vec<_>>();
+            let score = |alliance: &[usize]| alliance.iter().map(|i| if *i < 3 { 30 } else { 10 }).sum::<i32>();
+            let keys = |alliance: &[usize]| alliance.iter().map(|i| teams[*i].clone()).collect::<Vec<_>>();
+            match_with(&format!("2023cafr_qm{}", n), &keys(&red), &keys(&blue), Some((score(&red), score(&blue))))
+        }).collect::<Vec<_>>();
+
+        let opr = backtest(&mut OprPredictor::default(), &matches);
+        assert_eq!(opr.matches, 30);
+        assert!(opr.accuracy > 0.8);
+        assert!(opr.brier < 0.25);
+        assert_eq!(opr.calibration.iter().map(|b| b.count).sum::<usize>(), 30);
+
+        // OPRs updated match by match agree with those solved from every match at once
+        let mut predictor = OprPredictor::default();
+        matches.iter().for_each(|m| predictor.observe(m));
+        let options = OprOptions { include_playoffs: true, ridge: 0.1, ..Default::default() };
+        let solved = oprs(&matches, &options).unwrap();
+        for (team, opr) in solved.oprs.iter() {
+            assert!((predictor.opr(team).unwrap() - opr).abs() < 1e-6);
+        }
+
+        let mut blend = Blend { first: OprPredictor::default(), second: Elo::default(), weight: 0.5 };
+        let blended = backtest(&mut blend, &matches);
+        assert!(blended.accuracy > 0.5);
+        let prediction = blend.predict_teams(&teams[..3], &teams[3..]);
+        assert!(prediction.red_win_probability > 0.5);
+        assert!(prediction.red_score.unwrap() > prediction.blue_score.unwrap());
+    }
+}
@@ -0,0 +1,342 @@
+//! Monte Carlo simulation of the remaining qualification matches of an event, to estimate where
+//! each team will finish in the rankings
+
+use std::collections::HashMap;
+
+use crate::model::{
+    event::{EventRankings, PlayoffLevel},
+    matches::{Match, MatchAlliance, MatchWinner},
+    team::TeamKey,
+    Year,
+};
+
+use super::{math::Rng, opr::{oprs, OprError, OprOptions}};
+
+/// Ranking point and tiebreaker rules of a season
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeasonRules {
+    /// Ranking points for winning a match
+    pub win_rp: u32,
+    /// Ranking points for tying a match
+    pub tie_rp: u32,
+    /// Most bonus ranking points an alliance can earn in a match
+    pub max_bonus_rp: u32,
+    /// Name in the rankings' `sort_order_info` of the tiebreaker that is a team's average match
+    /// score, if the season has one. It is the only tiebreaker that changes with simulated
+    /// matches, as the others depend on parts of the score that are not modeled
+    pub score_tiebreaker: Option<&'static str>,
+}
+
+impl SeasonRules {
+    /// Get the ranking point rules of the given season
+    pub fn for_year(year: Year) -> Self {
+        match year.val() {
+            2025.. => Self { win_rp: 3, tie_rp: 1, max_bonus_rp: 3, score_tiebreaker: Some("Avg Match") },
+            2022.. => Self { win_rp: 2, tie_rp: 1, max_bonus_rp: 2, score_tiebreaker: Some("Avg Match") },
+            2016.. => Self { win_rp: 2, tie_rp: 1, max_bonus_rp: 2, score_tiebreaker: None },
+            _ => Self { win_rp: 2, tie_rp: 1, max_bonus_rp: 0, score_tiebreaker: None },
+        }
+    }
+}
+
+/// Model of the scores and bonus ranking points of alliances, from the OPRs of their teams and
+/// the rate at which each team's alliances have earned bonus ranking points
+#[derive(Clone, Debug)]
+pub struct ScoreModel {
+    pub oprs: HashMap<TeamKey, f64>,
+    /// Average bonus ranking points earned by each team's alliances
+    pub bonus_rp: HashMap<TeamKey, f64>,
+    /// Standard deviation of an alliance's score around the sum of its teams' OPRs
+    pub stdev: f64,
+    /// Contribution assumed for teams without an OPR
+    pub default_opr: f64,
+}
+
+impl ScoreModel {
+    /// Fit a model to the played qualification matches of an event
+    pub fn from_matches(matches: &[Match], rules: &SeasonRules) -> Result<Self, OprError> {
+        let options = OprOptions { ridge: 0.1, ..Default::default() };
+        let oprs = oprs(matches, &options)?.oprs;
+        let default_opr = oprs.values().sum::<f64>() / oprs.len().max(1) as f64;
+
+        let mut residuals = Vec::new();
+        let mut bonus = HashMap::<TeamKey, (f64, f64)>::new();
+        for m in matches.iter().filter(|m| m.comp_level == PlayoffLevel::QM && m.is_played()) {
+            for (color, alliance, won) in [("red", &m.alliances.red, MatchWinner::Red), ("blue", &m.alliances.blue, MatchWinner::Blue)] {
+                let predicted = alliance.team_keys.iter().map(|team| oprs.get(team).copied().unwrap_or(default_opr)).sum::<f64>();
+                residuals.push(f64::from(alliance.score.unwrap_or(0)) - predicted);
+
+                let rp = m
+                    .score_breakdown
+                    .as_ref()
+                    .and_then(|breakdown| breakdown.get(color)?.get("rp")?.as_f64());
+                if let Some(rp) = rp {
+                    let bonus_rp = rp - f64::from(result_rp(&m.winning_alliance, &won, rules));
+                    for team in alliance.team_keys.iter() {
+                        let (sum, count) = bonus.entry(team.clone()).or_default();
+                        *sum += bonus_rp.max(0.);
+                        *count += 1.;
+                    }
+                }
+            }
+        }
+
+        let stdev = (residuals.iter().map(|r| r * r).sum::<f64>() / residuals.len().max(1) as f64).sqrt();
+        let bonus_rp = bonus
+            .into_iter()
+            .map(|(team, (sum, count))| (team, sum / count))
+            .collect();
+        Ok(Self { oprs, bonus_rp, stdev, default_opr })
+    }
+
+    fn mean_score(&self, alliance: &MatchAlliance) -> f64 {
+        alliance.team_keys.iter().map(|team| self.oprs.get(team).copied().unwrap_or(self.default_opr)).sum()
+    }
+
+    fn mean_bonus(&self, alliance: &MatchAlliance) -> f64 {
+        let teams = alliance.team_keys.len().max(1) as f64;
+        alliance.team_keys.iter().map(|team| self.bonus_rp.get(team).copied().unwrap_or(0.)).sum::<f64>() / teams
+    }
+}
+
+/// Options for a ranking simulation
+#[derive(Clone, Debug)]
+pub struct SimOptions {
+    /// Number of times to simulate the remaining matches
+    pub iterations: usize,
+    /// Seed of the random number generator, so that simulations can be reproduced
+    pub seed: u64,
+    /// Ranking point rules, or `None` to use the rules of the event's season
+    pub rules: Option<SeasonRules>,
+}
+
+impl Default for SimOptions {
+    fn default() -> Self {
+        Self { iterations: 10_000, seed: 1280, rules: None }
+    }
+}
+
+/// Distribution of the final qualification rank of a team over all simulations
+#[derive(Clone, Debug, PartialEq)]
+pub struct RankDistribution {
+    /// Probability of finishing at each rank, starting from rank 1
+    pub probabilities: Vec<f64>,
+    pub mean_rank: f64,
+    /// Probability of finishing in the top 8, and so being an alliance captain before any
+    /// captains are picked
+    pub top_8: f64,
+}
+
+/// Running totals of a team used to rank it
+#[derive(Clone, Copy, Debug, Default)]
+struct Standing {
+    rp: f64,
+    /// Sum of the team's alliance scores, used for the season's score tiebreaker
+    points: f64,
+    played: f64,
+}
+
+/// Get the ranking points earned for the result of a match by an alliance that wins if the
+/// winner is `won`
+fn result_rp(winner: &MatchWinner, won: &MatchWinner, rules: &SeasonRules) -> u32 {
+    match winner {
+        winner if winner == won => rules.win_rp,
+        MatchWinner::Tie => rules.tie_rp,
+        _ => 0,
+    }
+}
+
+/// Simulate the unplayed qualification matches of an event, starting from its current rankings.
+/// Teams are ranked by average ranking points, then by each later sort order of the rankings in
+/// turn. The season's score tiebreaker is modeled by the average alliance score of each team's
+/// matches, and every other tiebreaker is held at the team's current value. Teams disqualified
+/// from a match earn no ranking points or score from it
+pub fn simulate(matches: &[Match], rankings: &EventRankings, model: &ScoreModel, options: &SimOptions) -> HashMap<TeamKey, RankDistribution> {
+    let Some(year) = matches.first().map(|m| m.event_key.year()) else { return HashMap::new() };
+    let rules = options.rules.unwrap_or_else(|| SeasonRules::for_year(year));
+    let tiebreakers = rankings.sort_order_info.len().max(1);
+    let score_tiebreaker = rules.score_tiebreaker.and_then(|name| rankings
+        .sort_order_info
+        .iter()
+        .position(|info| info.name.as_deref() == Some(name))
+    );
+
+    let mut teams = Vec::<TeamKey>::new();
+    let mut index = HashMap::<TeamKey, usize>::new();
+    let mut baseline = Vec::<Standing>::new();
+    let mut sort_orders = Vec::<Vec<f64>>::new();
+    let mut add = |team: &TeamKey, standing: Standing, orders: Option<Vec<f64>>| {
+        let i = *index.entry(team.clone()).or_insert_with(|| {
+            teams.push(team.clone());
+            baseline.push(Standing::default());
+            sort_orders.push(vec![0.; tiebreakers]);
+            teams.len() - 1
+        });
+        let current = &mut baseline[i];
+        current.rp += standing.rp;
+        current.points += standing.points;
+        current.played += standing.played;
+        if let Some(orders) = orders {
+            sort_orders[i] = orders;
+        }
+        i
+    };
+    for ranking in rankings.rankings.iter() {
+        let Some(team) = ranking.team_key.as_ref() else { continue };
+        let played = f64::from(ranking.matches_played.unwrap_or(0));
+        let sort_order = |i: usize| ranking.sort_orders.as_ref().and_then(|orders| orders.get(i).copied()).unwrap_or(0.);
+        let points = score_tiebreaker.map_or(0., sort_order) * played;
+        add(team, Standing { rp: sort_order(0) * played, points, played }, Some((0..tiebreakers).map(sort_order).collect()));
+    }
+
+    let remaining = matches
+        .iter()
+        .filter(|m| m.comp_level == PlayoffLevel::QM && !m.is_played())
+        .map(|m| {
+            let mut alliance = |alliance: &MatchAlliance| alliance
+                .team_keys
+                .iter()
+                .filter(|team| !alliance.surrogate_team_keys.contains(team))
+                .map(|team| (add(team, Standing::default(), None), alliance.dq_team_keys.contains(team)))
+                .collect::<Vec<_>>();
+            (
+                (alliance(&m.alliances.red), model.mean_score(&m.alliances.red), model.mean_bonus(&m.alliances.red)),
+                (alliance(&m.alliances.blue), model.mean_score(&m.alliances.blue), model.mean_bonus(&m.alliances.blue)),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut rng = Rng::new(options.seed);
+    let mut counts = vec![vec![0usize; teams.len()]; teams.len()];
+    let mut standings = baseline.clone();
+    let mut order = (0..teams.len()).collect::<Vec<_>>();
+    let mut tiebreak = vec![0f64; teams.len()];
+    for _ in 0..options.iterations {
+        standings.clone_from(&baseline);
+        for ((red, red_mean, red_bonus), (blue, blue_mean, blue_bonus)) in remaining.iter() {
+            let red_score = rng.normal(*red_mean, model.stdev).max(0.).round();
+            let blue_score = rng.normal(*blue_mean, model.stdev).max(0.).round();
+            let (red_rp, blue_rp) = match red_score.partial_cmp(&blue_score) {
+                Some(std::cmp::Ordering::Greater) => (rules.win_rp, 0),
+                Some(std::cmp::Ordering::Less) => (0, rules.win_rp),
+                _ => (rules.tie_rp, rules.tie_rp),
+            };
+            let mut bonus = |mean: f64| {
+                let mean = mean.clamp(0., f64::from(rules.max_bonus_rp));
+                mean.floor() + f64::from(u8::from(rng.uniform() < mean.fract()))
+            };
+            let (red_bonus, blue_bonus) = (bonus(*red_bonus), bonus(*blue_bonus));
+            for (alliance, rp, score) in [(red, f64::from(red_rp) + red_bonus, red_score), (blue, f64::from(blue_rp) + blue_bonus, blue_score)] {
+                for &(i, dq) in alliance {
+                    if !dq {
+                        standings[i].rp += rp;
+                        standings[i].points += score;
+                    }
+                    standings[i].played += 1.;
+                }
+            }
+        }
+
+        for t in tiebreak.iter_mut() {
+            *t = rng.uniform();
+        }
+        let average = |total: f64, played: f64| if played > 0. { total / played } else { 0. };
+        let tiebreaker = |team: usize, k: usize| match Some(k) == score_tiebreaker {
+            true => average(standings[team].points, standings[team].played),
+            false => sort_orders[team][k],
+        };
+        order.sort_by(|&a, &b| {
+            let (a_standing, b_standing) = (&standings[a], &standings[b]);
+            average(b_standing.rp, b_standing.played).total_cmp(&average(a_standing.rp, a_standing.played))
+                .then_with(|| (1..tiebreakers)
+                    .map(|k| tiebreaker(b, k).total_cmp(&tiebreaker(a, k)))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                )
+                .then(tiebreak[b].total_cmp(&tiebreak[a]))
+        });
+        for (rank, &team) in order.iter().enumerate() {
+            counts[team][rank] += 1;
+        }
+    }
+
+    let iterations = options.iterations.max(1) as f64;
+    teams
+        .into_iter()
+        .zip(counts)
+        .map(|(team, counts)| {
+            let probabilities = counts.iter().map(|count| *count as f64 / iterations).collect::<Vec<_>>();
+            let mean_rank = probabilities.iter().enumerate().map(|(rank, p)| (rank + 1) as f64 * p).sum();
+            let top_8 = probabilities.iter().take(8).sum();
+            (team, RankDistribution { probabilities, mean_rank, top_8 })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::{event::TeamEventStatusRankRanking, matches::test_util::{fixture, match_with}};
+
+    use super::*;
+
+    #[test]
+    fn simulate_remaining() {
+        let mut rankings: EventRankings = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_rankings.json")).unwrap();
+        let template = fixture();
+        let teams = ["frc1678", "frc1280", "frc5430", "frc8033", "frc701", "frc3189"].map(|t| t.parse::<TeamKey>().unwrap());
+        rankings.rankings = teams
+            .iter()
+            .enumerate()
+            .map(|(i, team)| TeamEventStatusRankRanking {
+                team_key: Some(team.clone()),
+                rank: Some(i as u32 + 1),
+                matches_played: Some(1),
+                sort_orders: Some(vec![0., 50.]),
+                ..rankings.rankings[0].clone()
+            })
+            .collect();
+
+        let rules = SeasonRules::for_year(Year::new(2023).unwrap());
+        let fitted = ScoreModel::from_matches(std::slice::from_ref(&template), &rules).unwrap();
+        assert!(fitted.bonus_rp.values().all(|rp| *rp >= 0. && *rp <= rules.max_bonus_rp as f64));
+        let model = ScoreModel {
+            oprs: teams.iter().enumerate().map(|(i, team)| (team.clone(), if i == 0 { 100. } else { 10. })).collect(),
+            bonus_rp: HashMap::new(),
+            stdev: 5.,
+            default_opr: 10.,
+        };
+
+        let unplayed = (2..=4).map(|n| {
+            let red = [teams[0].clone(), teams[n % 5 + 1].clone()];
+            let blue = teams[1..].iter().filter(|t| !red.contains(t)).take(2).cloned().collect::<Vec<_>>();
+            match_with(&format!("2023cafr_qm{}", n), &red, &blue, None)
+        }).collect::<Vec<_>>();
+
+        let options = SimOptions { iterations: 2000, ..Default::default() };
+        let result = simulate(&unplayed, &rankings, &model, &options);
+        let favorite = &result[&teams[0]];
+        assert!(favorite.probabilities[0] > 0.9);
+        assert_eq!(favorite.top_8, 1.);
+        for distribution in result.values() {
+            assert!((distribution.probabilities.iter().sum::<f64>() - 1.).abs() < 1e-9);
+        }
+        assert_eq!(simulate(&unplayed, &rankings, &model, &options), result);
+
+        // A team disqualified from its remaining matches earns nothing from them
+        let disqualified = unplayed.iter().cloned().map(|mut m| {
+            m.alliances.red.dq_team_keys = vec![teams[0].clone()];
+            m
+        }).collect::<Vec<_>>();
+        let result = simulate(&disqualified, &rankings, &model, &options);
+        assert_eq!(result[&teams[0]].probabilities[0], 0.);
+
+        // Tiebreakers that are not modeled keep their current values
+        for (i, ranking) in rankings.rankings.iter_mut().enumerate() {
+            ranking.sort_orders = Some(vec![0., 50., i as f64]);
+        }
+        let result = simulate(std::slice::from_ref(&template), &rankings, &model, &options);
+        for (i, team) in teams.iter().enumerate() {
+            assert_eq!(result[team].probabilities[teams.len() - 1 - i], 1.);
+        }
+    }
+}
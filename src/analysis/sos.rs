@@ -0,0 +1,203 @@
+//! Strength of the qualification schedule that each team played at an event
+
+use std::collections::HashMap;
+
+use crate::model::{
+    event::{EventOPRs, PlayoffLevel},
+    matches::{Match, MatchAlliance, MatchWinner},
+    team::TeamKey,
+};
+
+use super::{elo::Elo, math::erf};
+
+/// Trait implemented by sources of team ratings that can predict the winner of a match
+pub trait TeamRating {
+    /// Get the rating of a team, or `None` if the team is not rated
+    fn rating(&self, team: &TeamKey) -> Option<f64>;
+
+    /// Get the probability that an alliance with the `red` rating beats an alliance with the
+    /// `blue` rating, where the rating of an alliance is the sum of its teams' ratings
+    fn win_probability(&self, red: f64, blue: f64) -> f64;
+}
+
+impl TeamRating for Elo {
+    fn rating(&self, team: &TeamKey) -> Option<f64> {
+        Some(Elo::rating(self, team))
+    }
+
+    fn win_probability(&self, red: f64, blue: f64) -> f64 {
+        Elo::expected(red, blue)
+    }
+}
+
+/// Ratings that predict each alliance's score as the sum of its teams' ratings, such as OPRs
+#[derive(Clone, Debug)]
+pub struct ScoreRating {
+    pub ratings: HashMap<TeamKey, f64>,
+    /// Standard deviation of the actual margin of a match around the predicted margin
+    pub margin_stdev: f64,
+}
+
+impl ScoreRating {
+    /// Use the OPRs of an event as ratings, estimating how far match margins stray from their
+    /// predictions using the played matches of the event
+    pub fn from_oprs(oprs: &EventOPRs, matches: &[Match]) -> Self {
+        let sum = |alliance: &MatchAlliance| alliance.team_keys.iter().filter_map(|team| oprs.oprs.get(team)).sum::<f64>();
+        let errors = matches
+            .iter()
+            .filter(|m| m.is_played())
+            .map(|m| {
+                let actual = m.alliances.red.score.unwrap_or(0) - m.alliances.blue.score.unwrap_or(0);
+                (f64::from(actual) - (sum(&m.alliances.red) - sum(&m.alliances.blue))).powi(2)
+            })
+            .collect::<Vec<_>>();
+        let margin_stdev = (errors.iter().sum::<f64>() / errors.len().max(1) as f64).sqrt();
+        Self { ratings: oprs.oprs.clone(), margin_stdev }
+    }
+}
+
+impl TeamRating for ScoreRating {
+    fn rating(&self, team: &TeamKey) -> Option<f64> {
+        self.ratings.get(team).copied()
+    }
+
+    fn win_probability(&self, red: f64, blue: f64) -> f64 {
+        match self.margin_stdev > 0. {
+            true => 0.5 * (1. + erf((red - blue) / (self.margin_stdev * std::f64::consts::SQRT_2))),
+            false if red > blue => 1.,
+            false if red < blue => 0.,
+            false => 0.5,
+        }
+    }
+}
+
+/// How difficult a team's qualification schedule was, and how its results compare to what its
+/// alliances were expected to achieve
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduleStrength {
+    /// Number of qualification matches scheduled for the team, excluding matches it played as a
+    /// surrogate
+    pub matches: usize,
+    pub avg_partner_rating: f64,
+    pub avg_opponent_rating: f64,
+    /// Sum of the probabilities that the team's alliance won each of its played matches
+    pub expected_wins: f64,
+    /// Number of played matches won by the team's alliance, counting ties as half a win
+    pub actual_wins: f64,
+    /// Difference between the actual and expected wins of the team
+    pub luck: f64,
+    /// Total rating of the team's opponents minus the total rating of its partners, averaged
+    /// over its matches
+    pub difficulty: f64,
+    /// Rank of this team's difficulty among all teams at the event, where 1 is the hardest
+    pub difficulty_rank: usize,
+}
+
+/// Compute the strength of the schedule of every team in the qualification matches of an event.
+/// Partner and opponent ratings and difficulty cover every scheduled match, so that a schedule
+/// can be assessed before it is played, while wins cover only played matches. Unrated teams are
+/// given the average rating of the rated teams in the matches
+pub fn strength_of_schedule(matches: &[Match], ratings: &impl TeamRating) -> HashMap<TeamKey, ScheduleStrength> {
+    let quals = matches.iter().filter(|m| m.comp_level == PlayoffLevel::QM).collect::<Vec<_>>();
+    let rated = quals
+        .iter()
+        .flat_map(|m| m.alliances.red.team_keys.iter().chain(m.alliances.blue.team_keys.iter()))
+        .filter_map(|team| ratings.rating(team))
+        .collect::<Vec<_>>();
+    let average = rated.iter().sum::<f64>() / rated.len().max(1) as f64;
+    let rating = |team: &TeamKey| ratings.rating(team).unwrap_or(average);
+    let sum = |alliance: &MatchAlliance| alliance.team_keys.iter().map(rating).sum::<f64>();
+
+    #[derive(Default)]
+    struct Totals {
+        matches: usize,
+        partners: f64,
+        partner_count: usize,
+        opponents: f64,
+        opponent_count: usize,
+        expected: f64,
+        actual: f64,
+    }
+
+    let mut totals = HashMap::<TeamKey, Totals>::new();
+    for m in quals {
+        let (red, blue) = (&m.alliances.red, &m.alliances.blue);
+        for (alliance, opponent, won) in [(red, blue, MatchWinner::Red), (blue, red, MatchWinner::Blue)] {
+            let p = ratings.win_probability(sum(alliance), sum(opponent));
+            for team in alliance.team_keys.iter().filter(|team| !alliance.surrogate_team_keys.contains(team)) {
+                let total = totals.entry(team.clone()).or_default();
+                total.matches += 1;
+                total.partners += sum(alliance) - rating(team);
+                total.partner_count += alliance.team_keys.len() - 1;
+                total.opponents += sum(opponent);
+                total.opponent_count += opponent.team_keys.len();
+                if m.is_played() {
+                    total.expected += p;
+                    total.actual += match &m.winning_alliance {
+                        winner if *winner == won => 1.,
+                        MatchWinner::Tie => 0.5,
+                        _ => 0.,
+                    };
+                }
+            }
+        }
+    }
+
+    let mut report = totals
+        .into_iter()
+        .map(|(team, total)| {
+            let matches = total.matches.max(1) as f64;
+            (team, ScheduleStrength {
+                matches: total.matches,
+                avg_partner_rating: total.partners / total.partner_count.max(1) as f64,
+                avg_opponent_rating: total.opponents / total.opponent_count.max(1) as f64,
+                expected_wins: total.expected,
+                actual_wins: total.actual,
+                luck: total.actual - total.expected,
+                difficulty: (total.opponents - total.partners) / matches,
+                difficulty_rank: 0,
+            })
+        })
+        .collect::<Vec<_>>();
+    report.sort_by(|(_, a), (_, b)| b.difficulty.total_cmp(&a.difficulty));
+    report
+        .into_iter()
+        .enumerate()
+        .map(|(i, (team, mut strength))| {
+            strength.difficulty_rank = i + 1;
+            (team, strength)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::matches::test_util::fixture;
+
+    use super::*;
+
+    #[test]
+    fn schedule_strength() {
+        let m = fixture();
+        let oprs = EventOPRs {
+            oprs: m.alliances.red.team_keys.iter().map(|team| (team.clone(), 10.))
+                .chain(m.alliances.blue.team_keys.iter().map(|team| (team.clone(), 20.)))
+                .collect(),
+            dprs: HashMap::new(),
+            ccwms: HashMap::new(),
+        };
+        let ratings = ScoreRating::from_oprs(&oprs, std::slice::from_ref(&m));
+        let report = strength_of_schedule(std::slice::from_ref(&m), &ratings);
+
+        let red = &report[&m.alliances.red.team_keys[0]];
+        assert_eq!((red.avg_partner_rating, red.avg_opponent_rating), (10., 20.));
+        assert_eq!(red.difficulty, 40.);
+        assert_eq!(red.actual_wins, 0.);
+        assert!(red.expected_wins < 0.5 && red.luck < 0.);
+        assert!(red.difficulty_rank <= 3);
+        let blue = &report[&m.alliances.blue.team_keys[0]];
+        assert_eq!(blue.difficulty, 30. - 40.);
+        assert!(blue.difficulty_rank > 3);
+        assert!((red.expected_wins + blue.expected_wins - 1.).abs() < 1e-12);
+    }
+}
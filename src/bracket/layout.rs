@@ -0,0 +1,173 @@
+//! Layout of the series and pools played in each playoff format, before any results are known
+
+use crate::model::event::{PlayoffLevel, PlayoffType};
+
+use super::{Pool, PoolRanking, Series, Source::{self, *}};
+
+/// Series of the 8 alliance double elimination bracket, in the order they are played, as
+/// `(round, lower bracket, red, blue)`. The finals are played after these
+const DOUBLE_ELIM_8: [(u32, bool, Source, Source); 13] = [
+    (1, false, Seed(1), Seed(8)),
+    (1, false, Seed(4), Seed(5)),
+    (1, false, Seed(2), Seed(7)),
+    (1, false, Seed(3), Seed(6)),
+    (2, true, Loser(0), Loser(1)),
+    (2, true, Loser(2), Loser(3)),
+    (2, false, Winner(0), Winner(1)),
+    (2, false, Winner(2), Winner(3)),
+    (3, true, Loser(6), Winner(5)),
+    (3, true, Loser(7), Winner(4)),
+    (4, false, Winner(6), Winner(7)),
+    (4, true, Winner(9), Winner(8)),
+    (5, true, Loser(10), Winner(11)),
+];
+
+/// Series of the 4 alliance double elimination bracket, laid out like [`DOUBLE_ELIM_8`]
+const DOUBLE_ELIM_4: [(u32, bool, Source, Source); 5] = [
+    (1, false, Seed(1), Seed(4)),
+    (1, false, Seed(2), Seed(3)),
+    (2, true, Loser(0), Loser(1)),
+    (2, false, Winner(0), Winner(1)),
+    (3, true, Loser(3), Winner(2)),
+];
+
+/// Competition level and set number that TBA used for each series of [`DOUBLE_ELIM_8`] at
+/// events with the legacy double elimination format, followed by those of the finals
+const LEGACY_DOUBLE_ELIM_8: [(PlayoffLevel, u32); 14] = [
+    (PlayoffLevel::EF, 1),
+    (PlayoffLevel::EF, 2),
+    (PlayoffLevel::EF, 3),
+    (PlayoffLevel::EF, 4),
+    (PlayoffLevel::EF, 5),
+    (PlayoffLevel::EF, 6),
+    (PlayoffLevel::QF, 1),
+    (PlayoffLevel::QF, 2),
+    (PlayoffLevel::QF, 3),
+    (PlayoffLevel::QF, 4),
+    (PlayoffLevel::SF, 1),
+    (PlayoffLevel::SF, 2),
+    (PlayoffLevel::F, 1),
+    (PlayoffLevel::F, 2),
+];
+
+/// Get the series and pools of a playoff format, or `None` if the format has no fixed layout
+pub(super) fn layout(playoff_type: PlayoffType) -> Option<(Vec<Series>, Vec<Pool>)> {
+    let layout = match playoff_type {
+        PlayoffType::Bracket16Team => (single_elimination(&[1, 16, 8, 9, 4, 13, 5, 12, 2, 15, 7, 10, 3, 14, 6, 11]), vec![]),
+        PlayoffType::Bracket8Team => (single_elimination(&[1, 8, 4, 5, 2, 7, 3, 6]), vec![]),
+        PlayoffType::Bracket4Team => (single_elimination(&[1, 4, 2, 3]), vec![]),
+        PlayoffType::Bracket2Team | PlayoffType::Bo3Finals => (single_elimination(&[1, 2]), vec![]),
+        PlayoffType::Bo5Finals => {
+            let mut series = single_elimination(&[1, 2]);
+            series[0].wins_needed = 3;
+            (series, vec![])
+        }
+        PlayoffType::ModernDoubleElim8Team => (double_elimination(&DOUBLE_ELIM_8, modern_key(DOUBLE_ELIM_8.len())), vec![]),
+        PlayoffType::DoubleElim4Team => (double_elimination(&DOUBLE_ELIM_4, modern_key(DOUBLE_ELIM_4.len())), vec![]),
+        PlayoffType::DoubleElim8Team => (double_elimination(&DOUBLE_ELIM_8, |i| LEGACY_DOUBLE_ELIM_8[i].clone()), vec![]),
+        PlayoffType::RoundRobin6Team => {
+            let pool = pool("Round Robin", PlayoffLevel::SF, (1..=6).map(Seed).collect(), 2, 5, PoolRanking::Points);
+            let finals = series(PlayoffLevel::F, 1, "Finals", "Finals", Place(0, 1), Place(0, 2), 2);
+            (vec![finals], vec![pool])
+        }
+        PlayoffType::AvgScore8Team => (vec![], vec![
+            pool("Quarterfinals", PlayoffLevel::QF, (1..=8).map(Seed).collect(), 4, 2, PoolRanking::AverageScore),
+            pool("Semifinals", PlayoffLevel::SF, (1..=4).map(|place| Place(0, place)).collect(), 2, 2, PoolRanking::AverageScore),
+            pool("Finals", PlayoffLevel::F, (1..=2).map(|place| Place(1, place)).collect(), 1, 2, PoolRanking::AverageScore),
+        ]),
+        PlayoffType::Custom | PlayoffType::Unknown(_) => return None,
+    };
+    Some(layout)
+}
+
+fn series(comp_level: PlayoffLevel, set_number: u32, name: &str, round: &str, red: Source, blue: Source, wins_needed: u32) -> Series {
+    Series {
+        name: name.to_owned(),
+        round: round.to_owned(),
+        lower: false,
+        comp_level,
+        set_number,
+        red_source: red,
+        blue_source: blue,
+        red: None,
+        blue: None,
+        wins_needed,
+        red_wins: 0,
+        blue_wins: 0,
+        ties: 0,
+        matches: Vec::new(),
+        winner: None,
+    }
+}
+
+fn pool(name: &str, comp_level: PlayoffLevel, entrants: Vec<Source>, advance: usize, matches_each: u32, ranking: PoolRanking) -> Pool {
+    Pool {
+        name: name.to_owned(),
+        comp_level,
+        entrants,
+        advance,
+        matches_each,
+        ranking,
+        standings: Vec::new(),
+        matches: Vec::new(),
+        advancing: Vec::new(),
+    }
+}
+
+/// Lay out a best of three single elimination bracket, where the first round pairs up the given
+/// seeds in order and the winners of each pair of series meet in the next round
+fn single_elimination(seeds: &[u32]) -> Vec<Series> {
+    const ROUNDS: [(PlayoffLevel, &str, &str); 4] = [
+        (PlayoffLevel::EF, "Octofinal", "Octofinals"),
+        (PlayoffLevel::QF, "Quarterfinal", "Quarterfinals"),
+        (PlayoffLevel::SF, "Semifinal", "Semifinals"),
+        (PlayoffLevel::F, "Final", "Finals"),
+    ];
+
+    let mut result = Vec::<Series>::new();
+    let mut sources = seeds.iter().copied().map(Seed).collect::<Vec<_>>();
+    let first = ROUNDS.len() - seeds.len().trailing_zeros() as usize;
+    for (level, name, round) in ROUNDS[first..].iter().cloned() {
+        let start = result.len();
+        for (i, pair) in sources.chunks(2).enumerate() {
+            let name = match level {
+                PlayoffLevel::F => round.to_owned(),
+                _ => format!("{} {}", name, i + 1),
+            };
+            result.push(series(level.clone(), i as u32 + 1, &name, round, pair[0], pair[1], 2));
+        }
+        sources = (start..result.len()).map(Winner).collect();
+    }
+    result
+}
+
+/// Get the competition level and set number that TBA uses for each series of a double
+/// elimination bracket with the given number of series before the finals, at events since 2023.
+/// Those series are numbered semifinal sets and the finals are keyed as `f1mN`
+fn modern_key(series: usize) -> impl Fn(usize) -> (PlayoffLevel, u32) {
+    move |i| match i < series {
+        true => (PlayoffLevel::SF, i as u32 + 1),
+        false => (PlayoffLevel::F, 1),
+    }
+}
+
+/// Lay out a double elimination bracket from the given table, with a best of three finals
+/// between the last winner of the upper bracket and the last winner of the lower bracket.
+/// `key` gives the competition level and set number of the series at each index, including the
+/// finals
+fn double_elimination(table: &[(u32, bool, Source, Source)], key: impl Fn(usize) -> (PlayoffLevel, u32)) -> Vec<Series> {
+    let mut result = table
+        .iter()
+        .enumerate()
+        .map(|(i, (round, lower, red, blue))| {
+            let (level, set) = key(i);
+            let mut series = series(level, set, &format!("Match {}", i + 1), &format!("Round {}", round), *red, *blue, 1);
+            series.lower = *lower;
+            series
+        })
+        .collect::<Vec<_>>();
+    let last_upper = result.iter().rposition(|series| !series.lower).expect("Bracket has an upper bracket");
+    let (level, set) = key(result.len());
+    result.push(series(level, set, "Finals", "Finals", Winner(last_upper), Winner(result.len() - 1), 2));
+    result
+}
@@ -0,0 +1,482 @@
+//! Reconstruction of an event's playoff bracket from its alliances and matches
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::model::{
+    event::{EliminationAlliance, PlayoffLevel, PlayoffType},
+    matches::{Match, MatchKey, MatchWinner},
+    team::TeamKey,
+};
+
+mod layout;
+mod render;
+
+/// Errors that can occur while building a bracket
+#[derive(Debug, thiserror::Error)]
+pub enum BracketError {
+    #[error("Playoff type {0:?} does not have a known bracket layout")]
+    Unsupported(PlayoffType),
+}
+
+/// Where an alliance playing in a series or pool comes from. Series and pools are referred to
+/// by their index in [`Bracket::series`] and [`Bracket::pools`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// The alliance with the given number, from 1
+    Seed(u32),
+    /// The winner of a series
+    Winner(usize),
+    /// The loser of a series
+    Loser(usize),
+    /// The alliance finishing a pool at the given place, from 1
+    Place(usize, usize),
+}
+
+/// Head-to-head series between two alliances, won by the first alliance to win `wins_needed`
+/// matches
+#[derive(Clone, Debug, Serialize)]
+pub struct Series {
+    pub name: String,
+    pub round: String,
+    /// Whether this series is in the lower bracket of a double elimination bracket
+    pub lower: bool,
+    pub comp_level: PlayoffLevel,
+    pub set_number: u32,
+    pub red_source: Source,
+    pub blue_source: Source,
+    /// Number of the red alliance, once it is known
+    pub red: Option<u32>,
+    /// Number of the blue alliance, once it is known
+    pub blue: Option<u32>,
+    pub wins_needed: u32,
+    pub red_wins: u32,
+    pub blue_wins: u32,
+    pub ties: u32,
+    /// Matches of this series in play order
+    pub matches: Vec<MatchKey>,
+    pub winner: Option<u32>,
+}
+
+impl Series {
+    /// Get the alliance that lost this series, once it is decided
+    pub fn loser(&self) -> Option<u32> {
+        match self.winner {
+            Some(winner) if Some(winner) == self.red => self.blue,
+            Some(_) => self.red,
+            None => None,
+        }
+    }
+
+    /// Check if the given alliance is known to play in this series
+    pub fn contains(&self, alliance: u32) -> bool {
+        self.red == Some(alliance) || self.blue == Some(alliance)
+    }
+}
+
+/// How the alliances in a pool are ordered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolRanking {
+    /// Two points for each win and one for each tie, then total score
+    Points,
+    /// Average score of each alliance's matches
+    AverageScore,
+}
+
+/// Results of an alliance in a pool
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Standing {
+    pub alliance: u32,
+    pub played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    /// Total score of the alliance's played matches
+    pub score: i64,
+}
+
+impl Standing {
+    fn new(alliance: u32) -> Self {
+        Self { alliance, played: 0, wins: 0, losses: 0, ties: 0, score: 0 }
+    }
+
+    /// Get the average score of the alliance's played matches
+    pub fn average(&self) -> f64 {
+        self.score as f64 / self.played.max(1) as f64
+    }
+}
+
+/// Group of alliances that each play a number of matches, after which the best placed alliances
+/// advance
+#[derive(Clone, Debug, Serialize)]
+pub struct Pool {
+    pub name: String,
+    /// Competition level of every match in this pool
+    pub comp_level: PlayoffLevel,
+    pub entrants: Vec<Source>,
+    /// Number of alliances that advance out of this pool
+    pub advance: usize,
+    /// Number of matches each alliance plays in this pool
+    pub matches_each: u32,
+    pub ranking: PoolRanking,
+    /// Standings of the alliances in this pool, best first
+    pub standings: Vec<Standing>,
+    pub matches: Vec<MatchKey>,
+    /// Alliances that advance out of this pool in order of place, which is empty until every
+    /// alliance has played all of its matches
+    pub advancing: Vec<u32>,
+}
+
+impl Pool {
+    /// Check if every alliance in this pool has played all of its matches
+    pub fn is_complete(&self) -> bool {
+        !self.advancing.is_empty()
+    }
+}
+
+/// A series or pool, referred to by its index in [`Bracket::series`] or [`Bracket::pools`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Series(usize),
+    Pool(usize),
+}
+
+/// Result of a series or pool for a single alliance
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Outcome {
+    pub stage: Stage,
+    /// Whether the alliance won the series or advanced out of the pool
+    pub advances: bool,
+}
+
+/// Playoff bracket of an event, with the results of every match played so far
+#[derive(Clone, Debug, Serialize)]
+pub struct Bracket {
+    pub playoff_type: PlayoffType,
+    /// Alliances in order of their number
+    pub alliances: Vec<EliminationAlliance>,
+    /// Series in the order they are played
+    pub series: Vec<Series>,
+    /// Pools in the order they are played, which are all played before any series
+    pub pools: Vec<Pool>,
+}
+
+impl Bracket {
+    /// Build the bracket of a playoff format from an event's alliances and matches, which may
+    /// include qualification matches and matches that have not been played yet. The alliance
+    /// playing on each side of a match is identified by its teams, so a series' alliances
+    /// are taken from its matches where the layout of the bracket cannot tell them apart
+    pub fn new(playoff_type: PlayoffType, alliances: &[EliminationAlliance], matches: &[Match]) -> Result<Self, BracketError> {
+        let (series, pools) = layout::layout(playoff_type).ok_or(BracketError::Unsupported(playoff_type))?;
+        let mut bracket = Self { playoff_type, alliances: alliances.to_vec(), series, pools };
+
+        let mut matches = matches.iter().filter(|m| m.comp_level != PlayoffLevel::QM).collect::<Vec<_>>();
+        matches.sort_by(|a, b| a.cmp_play_order(b));
+        for i in 0..bracket.pools.len() {
+            bracket.resolve_pool(i, &matches);
+        }
+        for i in 0..bracket.series.len() {
+            bracket.resolve_series(i, &matches);
+        }
+        Ok(bracket)
+    }
+
+    /// Get the number of the alliance that a team plays for, including as a backup
+    pub fn alliance_of(&self, team: &TeamKey) -> Option<u32> {
+        self.alliances
+            .iter()
+            .position(|alliance| {
+                alliance.picks.contains(team) || alliance.backup.as_ref().and_then(|backup| backup.in_.as_ref()) == Some(team)
+            })
+            .map(|i| i as u32 + 1)
+    }
+
+    /// Get the series that a playoff match belongs to
+    pub fn series_of(&self, m: &MatchKey) -> Option<&Series> {
+        self.series.iter().find(|series| series.matches.contains(m))
+    }
+
+    /// Get the alliance that won the playoffs, once it is known
+    pub fn champion(&self) -> Option<u32> {
+        match self.series.last() {
+            Some(finals) => finals.winner,
+            None => self.pools.last()?.advancing.first().copied(),
+        }
+    }
+
+    /// Iterate over the series that are ready to be played, because both of their alliances
+    /// are known but neither has won yet, in the order they are played
+    pub fn up_next(&self) -> impl Iterator<Item = &Series> {
+        self.series
+            .iter()
+            .filter(|series| series.winner.is_none() && series.red.is_some() && series.blue.is_some())
+    }
+
+    /// Get the series or pool that an alliance is playing in or waiting to play in, or `None` if
+    /// it has been eliminated or won the playoffs
+    pub fn current(&self, alliance: u32) -> Option<Stage> {
+        let pool = self.pools.iter().position(|pool| {
+            !pool.is_complete() && pool.standings.iter().any(|standing| standing.alliance == alliance)
+        });
+        let series = || self.series.iter().position(|series| series.winner.is_none() && series.contains(alliance));
+        pool.map(Stage::Pool).or_else(|| series().map(Stage::Series))
+    }
+
+    /// Get the results of every series and pool that an alliance has finished, in play order
+    pub fn path(&self, alliance: u32) -> Vec<Outcome> {
+        let pools = self.pools
+            .iter()
+            .enumerate()
+            .filter(|(_, pool)| pool.is_complete() && pool.standings.iter().any(|standing| standing.alliance == alliance))
+            .map(|(i, pool)| Outcome { stage: Stage::Pool(i), advances: pool.advancing.contains(&alliance) });
+        let series = self.series.iter().enumerate().filter_map(|(i, series)| {
+            let winner = series.winner?;
+            series.contains(alliance).then_some(Outcome { stage: Stage::Series(i), advances: winner == alliance })
+        });
+        pools.chain(series).collect()
+    }
+
+    /// Get every sequence of results, starting from the alliance's current series or pool, that
+    /// would eliminate it from the playoffs. Each sequence ends with the result that eliminates
+    /// the alliance, and the sequences are empty if it is already eliminated or has won
+    pub fn elimination_scenarios(&self, alliance: u32) -> Vec<Vec<Outcome>> {
+        fn explore(bracket: &Bracket, stage: Stage, path: &mut Vec<Outcome>, scenarios: &mut Vec<Vec<Outcome>>) {
+            for advances in [true, false] {
+                path.push(Outcome { stage, advances });
+                match bracket.next_stage(stage, advances) {
+                    Some(next) => explore(bracket, next, path, scenarios),
+                    None if !advances => scenarios.push(path.clone()),
+                    None => (),
+                }
+                path.pop();
+            }
+        }
+
+        let mut scenarios = Vec::new();
+        if let Some(stage) = self.current(alliance) {
+            explore(self, stage, &mut Vec::new(), &mut scenarios);
+        }
+        scenarios
+    }
+
+    /// Get the series or pool that an alliance plays in after the given result, or `None` if the
+    /// result eliminates it or wins the playoffs
+    pub fn next_stage(&self, stage: Stage, advances: bool) -> Option<Stage> {
+        let fed = |source: &Source| match (stage, *source) {
+            (Stage::Series(i), Source::Winner(j)) => advances && i == j,
+            (Stage::Series(i), Source::Loser(j)) => !advances && i == j,
+            (Stage::Pool(i), Source::Place(j, _)) => advances && i == j,
+            _ => false,
+        };
+        let pool = self.pools.iter().position(|pool| pool.entrants.iter().any(fed));
+        let series = || self.series.iter().position(|series| fed(&series.red_source) || fed(&series.blue_source));
+        pool.map(Stage::Pool).or_else(|| series().map(Stage::Series))
+    }
+
+    /// Get the alliance that a source refers to, once it is known
+    fn resolve(&self, source: &Source) -> Option<u32> {
+        match *source {
+            Source::Seed(n) => (n as usize <= self.alliances.len()).then_some(n),
+            Source::Winner(i) => self.series[i].winner,
+            Source::Loser(i) => self.series[i].loser(),
+            Source::Place(i, place) => self.pools[i].advancing.get(place - 1).copied(),
+        }
+    }
+
+    /// Get the alliance that most of the given teams play for
+    fn alliance_of_teams(&self, teams: &[TeamKey]) -> Option<u32> {
+        let mut votes = HashMap::<u32, usize>::new();
+        for alliance in teams.iter().filter_map(|team| self.alliance_of(team)) {
+            *votes.entry(alliance).or_default() += 1;
+        }
+        votes.into_iter().max_by_key(|(alliance, votes)| (*votes, std::cmp::Reverse(*alliance))).map(|(alliance, _)| alliance)
+    }
+
+    fn resolve_series(&mut self, i: usize, matches: &[&Match]) {
+        let series = &self.series[i];
+        let (mut red, mut blue) = (self.resolve(&series.red_source), self.resolve(&series.blue_source));
+        let matches = matches
+            .iter()
+            .filter(|m| m.comp_level == series.comp_level && m.set_number == series.set_number)
+            .collect::<Vec<_>>();
+        let sides = |m: &Match| (self.alliance_of_teams(&m.alliances.red.team_keys), self.alliance_of_teams(&m.alliances.blue.team_keys));
+        if let Some((Some(r), Some(b))) = matches.first().map(|m| sides(m)) {
+            (red, blue) = (Some(r), Some(b));
+        }
+
+        let (mut red_wins, mut blue_wins, mut ties) = (0, 0, 0);
+        for m in matches.iter().filter(|m| m.is_played()) {
+            let (r, b) = sides(m);
+            let winner = match m.winning_alliance {
+                MatchWinner::Red => r,
+                MatchWinner::Blue => b,
+                _ => {
+                    ties += 1;
+                    continue
+                }
+            };
+            match winner {
+                Some(winner) if Some(winner) == red => red_wins += 1,
+                Some(winner) if Some(winner) == blue => blue_wins += 1,
+                _ => (),
+            }
+        }
+        let keys = matches.iter().map(|m| m.key.clone()).collect();
+
+        let series = &mut self.series[i];
+        series.red = red;
+        series.blue = blue;
+        series.red_wins = red_wins;
+        series.blue_wins = blue_wins;
+        series.ties = ties;
+        series.matches = keys;
+        series.winner = match (red_wins, blue_wins) {
+            (wins, _) if wins >= series.wins_needed => red,
+            (_, wins) if wins >= series.wins_needed => blue,
+            _ => None,
+        };
+    }
+
+    fn resolve_pool(&mut self, i: usize, matches: &[&Match]) {
+        let pool = &self.pools[i];
+        let mut standings = HashMap::<u32, Standing>::new();
+        for alliance in pool.entrants.iter().filter_map(|source| self.resolve(source)) {
+            standings.insert(alliance, Standing::new(alliance));
+        }
+
+        let matches = matches.iter().filter(|m| m.comp_level == pool.comp_level).collect::<Vec<_>>();
+        for m in matches.iter() {
+            let sides = [
+                (&m.alliances.red, MatchWinner::Red),
+                (&m.alliances.blue, MatchWinner::Blue),
+            ];
+            for (side, color) in sides {
+                let Some(alliance) = self.alliance_of_teams(&side.team_keys) else { continue };
+                let standing = standings.entry(alliance).or_insert_with(|| Standing::new(alliance));
+                if !m.is_played() {
+                    continue
+                }
+                standing.played += 1;
+                standing.score += i64::from(side.score.unwrap_or(0));
+                match &m.winning_alliance {
+                    winner if *winner == color => standing.wins += 1,
+                    MatchWinner::Red | MatchWinner::Blue => standing.losses += 1,
+                    _ => standing.ties += 1,
+                }
+            }
+        }
+
+        let mut standings = standings.into_values().collect::<Vec<_>>();
+        standings.sort_by_key(|standing| standing.alliance);
+        match pool.ranking {
+            PoolRanking::Points => standings.sort_by(|a, b| {
+                (2 * b.wins + b.ties).cmp(&(2 * a.wins + a.ties)).then(b.score.cmp(&a.score))
+            }),
+            PoolRanking::AverageScore => standings.sort_by(|a, b| b.average().total_cmp(&a.average())),
+        }
+        let complete = standings.len() >= pool.entrants.len()
+            && standings.iter().all(|standing| standing.played >= pool.matches_each);
+        let advancing = match complete {
+            true => standings.iter().take(pool.advance).map(|standing| standing.alliance).collect(),
+            false => Vec::new(),
+        };
+        let keys = matches.iter().map(|m| m.key.clone()).collect();
+
+        let pool = &mut self.pools[i];
+        pool.standings = standings;
+        pool.matches = keys;
+        pool.advancing = advancing;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::matches::test_util::{match_with, teams};
+
+    use super::*;
+
+    /// Get the given number of alliances, each made of the next three team numbers
+    fn alliances(count: u32) -> Vec<EliminationAlliance> {
+        (1..=count)
+            .map(|n| EliminationAlliance {
+                name: Some(format!("Alliance {}", n)),
+                backup: None,
+                declined: Vec::new(),
+                picks: teams(3 * n - 2..=3 * n),
+                status: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn double_elimination() {
+        let alliances = alliances(8);
+        // The better seeded alliance wins each of the first eight matches
+        let results = [(1, 8), (4, 5), (2, 7), (3, 6), (5, 8), (6, 7), (1, 4), (2, 3)];
+        let matches = results.iter().enumerate().map(|(i, (winner, loser))| {
+            let picks = |seed: &usize| &alliances[seed - 1].picks;
+            match_with(&format!("2023cafr_sf{}m1", i + 1), picks(loser), picks(winner), Some((47, 61)))
+        }).collect::<Vec<_>>();
+
+        let bracket = Bracket::new(PlayoffType::ModernDoubleElim8Team, &alliances, &matches).unwrap();
+        assert_eq!(bracket.series.len(), 14);
+        assert_eq!((bracket.series[8].red, bracket.series[8].blue), (Some(4), Some(6)));
+        let next = bracket.up_next().map(|series| series.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(next, ["Match 9", "Match 10", "Match 11"]);
+        assert_eq!(bracket.champion(), None);
+
+        assert_eq!(bracket.current(8), None);
+        assert_eq!(bracket.path(8), [
+            Outcome { stage: Stage::Series(0), advances: false },
+            Outcome { stage: Stage::Series(4), advances: false },
+        ]);
+        assert_eq!(bracket.elimination_scenarios(4).iter().map(Vec::len).collect::<Vec<_>>(), [4, 3, 2, 1]);
+        let scenarios = bracket.elimination_scenarios(1);
+        assert_eq!(scenarios.len(), 3);
+        assert_eq!(scenarios[0], [
+            Outcome { stage: Stage::Series(10), advances: true },
+            Outcome { stage: Stage::Series(13), advances: false },
+        ]);
+
+        let ascii = bracket.to_ascii();
+        assert!(ascii.contains("Round 3 (lower bracket)"));
+        assert!(ascii.contains("W Match 9"));
+        let json = bracket.to_json();
+        assert_eq!(json["series"][13]["name"], "Finals");
+        assert_eq!(json["series"][6]["winner"], 1);
+    }
+
+    #[test]
+    fn double_elimination_finals() {
+        let alliances = alliances(4);
+        // Alliance 2 drops to the lower bracket, then beats alliance 1 in the finals in three
+        let results = [
+            ("sf1m1", 1, 4),
+            ("sf2m1", 2, 3),
+            ("sf3m1", 3, 4),
+            ("sf4m1", 1, 2),
+            ("sf5m1", 2, 3),
+            ("f1m1", 2, 1),
+            ("f1m2", 1, 2),
+            ("f1m3", 2, 1),
+        ];
+        let matches = results.iter().map(|(key, winner, loser)| {
+            let picks = |seed: &usize| &alliances[seed - 1].picks;
+            match_with(&format!("2023cafr_{}", key), picks(winner), picks(loser), Some((61, 47)))
+        }).collect::<Vec<_>>();
+
+        let bracket = Bracket::new(PlayoffType::DoubleElim4Team, &alliances, &matches).unwrap();
+        let finals = bracket.series.last().unwrap();
+        assert_eq!((finals.comp_level.clone(), finals.set_number), (PlayoffLevel::F, 1));
+        assert_eq!(finals.matches.len(), 3);
+        assert!(finals.contains(1) && finals.contains(2));
+        assert_eq!(finals.loser(), Some(1));
+        assert_eq!(finals.winner, Some(2));
+        assert_eq!(bracket.series_of(&matches[7].key).map(|series| series.name.as_str()), Some("Finals"));
+        assert_eq!(bracket.champion(), Some(2));
+    }
+}
@@ -0,0 +1,68 @@
+//! Text and JSON renderings of a bracket
+
+use std::fmt::Write;
+
+use super::{Bracket, PoolRanking, Source};
+
+impl Bracket {
+    /// Render the alliances, series and pools of this bracket as plain text, with one line for
+    /// each series and a table of standings for each pool. Winners are marked with `*`
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::new();
+        let label = |alliance: Option<u32>, source: &Source, winner: Option<u32>| match alliance {
+            Some(alliance) if Some(alliance) == winner => format!("A{}*", alliance),
+            Some(alliance) => format!("A{}", alliance),
+            None => self.describe(source),
+        };
+
+        writeln!(out, "Alliances").unwrap();
+        for (i, alliance) in self.alliances.iter().enumerate() {
+            let teams = alliance.picks.iter().map(|team| team.as_ref()).collect::<Vec<_>>().join(" ");
+            writeln!(out, "  A{:<3} {}", i + 1, teams).unwrap();
+        }
+
+        for pool in self.pools.iter() {
+            writeln!(out, "{} (top {} advance)", pool.name, pool.advance).unwrap();
+            for standing in pool.standings.iter() {
+                let marker = if pool.advancing.contains(&standing.alliance) { "*" } else { "" };
+                let record = format!("{}-{}-{}", standing.wins, standing.losses, standing.ties);
+                let score = match pool.ranking {
+                    PoolRanking::Points => format!("{} pts", standing.score),
+                    PoolRanking::AverageScore => format!("{:.1} avg", standing.average()),
+                };
+                writeln!(out, "  {:<5} {:<8} {}", format!("A{}{}", standing.alliance, marker), record, score).unwrap();
+            }
+        }
+
+        let mut heading = None;
+        for series in self.series.iter() {
+            let round = match series.lower {
+                true => format!("{} (lower bracket)", series.round),
+                false => series.round.clone(),
+            };
+            if heading.as_ref() != Some(&round) {
+                writeln!(out, "{}", round).unwrap();
+                heading = Some(round);
+            }
+            let red = label(series.red, &series.red_source, series.winner);
+            let blue = label(series.blue, &series.blue_source, series.winner);
+            writeln!(out, "  {:<16} {:>16} {} - {} {}", series.name, red, series.red_wins, series.blue_wins, blue).unwrap();
+        }
+        out
+    }
+
+    /// Render this bracket as JSON, with the same structure as its fields
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("Bracket can always be serialized")
+    }
+
+    /// Describe where an alliance that is not known yet will come from
+    fn describe(&self, source: &Source) -> String {
+        match *source {
+            Source::Seed(n) => format!("A{}", n),
+            Source::Winner(i) => format!("W {}", self.series[i].name),
+            Source::Loser(i) => format!("L {}", self.series[i].name),
+            Source::Place(i, place) => format!("#{} {}", place, self.pools[i].name),
+        }
+    }
+}
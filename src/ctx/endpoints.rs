@@ -4,13 +4,20 @@ use moka::sync::Cache;
 
 use serde::de::DeserializeOwned;
 
-use crate::{Error, model::{team::{Team, SimpleTeam, TeamKey}, Year, event::{EventKey, TeamEventStatus, Event, EliminationAlliance, EventOPRs, EventDistrictPoints}, matches::{Match, MatchKey}}};
-use std::collections::HashMap;
+use crate::{Error, model::{award::Award, team::{Team, SimpleTeam, TeamKey}, Year, event::{EventKey, TeamEventStatus, Event, SimpleEvent, EliminationAlliance, EventOPRs, EventDistrictPoints, EventRankings}, matches::{Match, MatchKey}, with_strictness}};
+use std::{collections::{HashMap, HashSet}, sync::Arc, time::{Duration, Instant}};
 use async_trait::async_trait;
 
 use super::Context;
 
-const BASE_ENDPOINT: &str = "http://www.thebluealliance.com/api/v3/";
+pub(super) const BASE_ENDPOINT: &str = "http://www.thebluealliance.com/api/v3/";
+
+/// Time for which a value seeded from the response of another endpoint is served without a
+/// request
+pub const SEED_TTL: Duration = Duration::from_secs(60);
+
+/// Largest response body that will be read from the API, in bytes
+const MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
 
 /// Trait implemented by all structures that represent endpoints of the TBA API with methods to
 /// make requests using given parameters
@@ -26,8 +33,19 @@ pub trait EndPoint: Sized {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct EndPointCacheEntry<T> {
     pub val: T,
-    /// Version of this cached entry
-    pub etag: String,
+    /// Version of this cached entry, sent with the next request for it so that the API can
+    /// answer without a body if it has not changed
+    pub etag: Option<String>,
+    /// Time until which this entry is served without a request, set for entries seeded from
+    /// the response of another endpoint
+    pub fresh_until: Option<Instant>,
+}
+
+impl<T> EndPointCacheEntry<T> {
+    /// Check if this entry can be served without asking the API whether it has changed
+    pub fn is_fresh(&self) -> bool {
+        self.fresh_until.is_some_and(|until| Instant::now() < until)
+    }
 }
 
 /// A collection of API endpoints that each cache requests made to them
@@ -36,9 +54,70 @@ pub struct EndPoints {
     pub teams: TeamsEndPoint,
     pub team: TeamEndPoint,
     pub event: EventEndPoint,
+    pub events: EventsEndPoint,
     pub matches: MatchEndPoint,
 }
 
+impl EndPoints {
+    /// Mark the data of the given team as stale, so that the next request for it is not served
+    /// from the cache
+    pub fn invalidate_team(&self, team: &TeamKey) {
+        self.team.team.invalidate(&(team.clone(),));
+        self.team.events_by_year.invalidate_if(|(key, _)| key == team);
+        self.team.event_statuses_by_year.invalidate_if(|(key, _)| key == team);
+        self.team.matches_by_year.invalidate_if(|(key, _)| key == team);
+    }
+
+    /// Mark the data of the given match as stale, so that the next request for it is not served
+    /// from the cache
+    pub fn invalidate_match(&self, key: &MatchKey) {
+        self.matches.matches.invalidate(&(key.clone(),));
+    }
+
+    /// Mark all data that depends on the given event as stale, including its matches, rankings,
+    /// alliances, OPRs, district points, awards, team lists and team statuses, and the match
+    /// lists and statuses in the event's year of the teams attending it. If none of the event's
+    /// team lists are cached, those of every team in the year are marked stale instead
+    pub fn invalidate_event(&self, event: &EventKey) {
+        let params = (event.clone(),);
+        let roster = self.cached_roster(&params);
+        self.event.event.invalidate(&params);
+        self.event.simple.invalidate(&params);
+        self.event.alliances.invalidate(&params);
+        self.event.oprs.invalidate(&params);
+        self.event.rankings.invalidate(&params);
+        self.event.district_points.invalidate(&params);
+        self.event.awards.invalidate(&params);
+        self.event.teams.invalidate(&params);
+        self.event.simple_teams.invalidate(&params);
+        self.event.team_keys.invalidate(&params);
+        self.event.team_statuses.invalidate(&params);
+        self.event.matches.invalidate(&params);
+        self.event.match_keys.invalidate(&params);
+
+        let year = event.year();
+        let attending = |(team, team_year): &(TeamKey, Year)| *team_year == year
+            && roster.as_ref().is_none_or(|roster| roster.contains(team));
+        self.events.by_year.invalidate(&(year,));
+        self.team.event_statuses_by_year.invalidate_if(attending);
+        self.team.matches_by_year.invalidate_if(attending);
+
+        self.matches.matches.invalidate_if(|(key,)| key.event() == *event);
+    }
+
+    /// Get the keys of the teams attending an event from whichever of its team lists is cached
+    fn cached_roster(&self, params: &(EventKey,)) -> Option<HashSet<TeamKey>> {
+        if let Some(entry) = self.event.team_keys.cache.get(params) {
+            return Some(entry.val.iter().cloned().collect())
+        }
+        if let Some(entry) = self.event.simple_teams.cache.get(params) {
+            return Some(entry.val.iter().map(|team| team.key.clone()).collect())
+        }
+        let entry = self.event.teams.cache.get(params)?;
+        Some(entry.val.iter().map(|team| team.simple.key.clone()).collect())
+    }
+}
+
 /// Structure representing requests made to the /teams endpoint
 #[derive(Default)]
 pub struct TeamsEndPoint {
@@ -61,6 +140,12 @@ pub struct TeamsEndPoint {
 pub struct TeamEndPoint {
     /// Represents /team/{team_key}
     pub team: TeamEP,
+    /// Represents /team/{team_key}/events/{year}
+    pub events_by_year: TeamEventsByYearEP,
+    /// Represents /team/{team_key}/events/{year}/statuses
+    pub event_statuses_by_year: EventStatusByYearEP,
+    /// Represents /team/{team_key}/matches/{year}
+    pub matches_by_year: TeamMatchesByYearEP,
 }
 
 /// Container with all /event/ endpoints modelled
@@ -74,8 +159,16 @@ pub struct EventEndPoint {
     pub alliances: EliminationAlliancesEP,
     /// Represents the /event/{event_key}/oprs endpoint
     pub oprs: EventOPRsEP,
+    /// Represents the /event/{event_key}/rankings endpoint
+    pub rankings: EventRankingsEP,
     /// Represents the /event/{event_key}/district_points endpoint
     pub district_points: EventDistrictPointsEP,
+    /// Represents the /event/{event_key}/awards endpoint
+    pub awards: EventAwardsEP,
+    /// Represents the /event/{event_key}/teams endpoint
+    pub teams: EventTeamsEP,
+    /// Represents the /event/{event_key}/teams/simple endpoint
+    pub simple_teams: EventSimpleTeamsEP,
     /// Represents the /event/{event_key}/teams/keys endpoint
     pub team_keys: EventTeamKeysEP,
     /// Represents the /event/{event_key}/teams/statuses endpoint
@@ -86,6 +179,13 @@ pub struct EventEndPoint {
     pub match_keys: EventMatchKeysEP,
 }
 
+/// Container with all /events/ endpoints modelled
+#[derive(Default)]
+pub struct EventsEndPoint {
+    /// Represents the /events/{year} endpoint
+    pub by_year: EventsByYearEP,
+}
+
 #[derive(Default)]
 pub struct MatchEndPoint {
     /// Represents the /match/{match_key} endpoint
@@ -94,6 +194,9 @@ pub struct MatchEndPoint {
 
 macro_rules! endpoint {
     ($name:ident: ($($params:ty),+) => $val:ty where ($($names:ident),+) $path:literal) => {
+        endpoint!{$name: ($($params),+) => $val where ($($names),+) $path then (_val, _ctx) {}}
+    };
+    ($name:ident: ($($params:ty),+) => $val:ty where ($($names:ident),+) $path:literal then ($vali:ident, $ctxi:ident) $then:block) => {
         pub struct $name { cache: Cache<($($params),+,), EndPointCacheEntry<::std::sync::Arc<$val>>> }
         #[async_trait(?Send)]
         impl self::EndPoint for $name {
@@ -101,13 +204,64 @@ macro_rules! endpoint {
             type Value = ::std::sync::Arc<$val>;
             async fn get(&self, params: ($($params),+,), ctx: &Context) -> ::std::result::Result<Self::Value, Error> {
                 let ($(ref $names),+,) = params;
-                let path = ::std::format!($path, BASE_ENDPOINT);
-                get_ep::<Self>(
+                let path = ::std::format!($path, ctx.base);
+                let ($vali, changed) = get_ep::<Self>(
                     path,
                     params,
                     &self.cache,
                     ctx
-                ).await
+                ).await?;
+                let $ctxi = ctx;
+                if changed $then
+                Ok($vali)
+            }
+        }
+
+        impl $name {
+            /// Insert a value into this endpoint's cache that was obtained from another endpoint,
+            /// so that requests with the same parameters in the next [SEED_TTL] are served
+            /// without a request
+            pub fn seed(&self, params: ($($params),+,), val: ::std::sync::Arc<$val>) {
+                self.cache.insert(params, EndPointCacheEntry { val, etag: None, fresh_until: Some(Instant::now() + SEED_TTL) });
+            }
+
+            /// Remove the cached value for the given parameters so that the next request is made
+            /// without an ETag
+            pub fn invalidate(&self, params: &($($params),+,)) {
+                self.cache.invalidate(params);
+            }
+
+            /// Remove every cached value whose parameters match the given predicate
+            pub fn invalidate_if(&self, pred: impl Fn(&($($params),+,)) -> bool) {
+                for (params, _) in self.cache.iter() {
+                    if pred(&params) {
+                        self.cache.invalidate(&*params);
+                    }
+                }
+            }
+
+            /// Remove every cached value from this endpoint
+            pub fn invalidate_all(&self) {
+                self.cache.invalidate_all();
+            }
+
+            /// Make a request that the API answers without a body if the resource still has the
+            /// given ETag. Returns the new value and ETag of the resource, or `None` if it has not
+            /// changed. A new value replaces the cached value for the given parameters
+            pub async fn get_if_changed(
+                &self,
+                params: ($($params),+,),
+                etag: Option<&str>,
+                ctx: &Context,
+            ) -> ::std::result::Result<Option<(::std::sync::Arc<$val>, Option<String>)>, Error> {
+                let ($(ref $names),+,) = params;
+                let path = ::std::format!($path, ctx.base);
+                let Some((val, etag)) = get_if_changed::<Self>(path, etag, ctx).await? else { return Ok(None) };
+                self.cache.insert(params, EndPointCacheEntry { val: val.clone(), etag: etag.clone(), fresh_until: None });
+                let $vali = val;
+                let $ctxi = ctx;
+                $then;
+                Ok(Some(($vali, etag)))
             }
         }
 
@@ -126,73 +280,254 @@ endpoint!{TeamPageByYearEP: (Year, usize) => Vec<Team> where (year, page_num) "{
 endpoint!{SimpleTeamPageByYearEP: (Year, usize) => Vec<SimpleTeam> where (year, page_num) "{}/teams/{year}/{page_num}/simple"}
 endpoint!{KeysTeamPageByYearEP: (Year, usize) => Vec<TeamKey> where (year, page_num) "{}/teams/{year}/{page_num}/keys"}
 endpoint!{
-    EventStatusByYearEP: (TeamKey, Year) => HashMap<EventKey, TeamEventStatus>
+    EventStatusByYearEP: (TeamKey, Year) => HashMap<EventKey, Option<TeamEventStatus>>
     where (team_key, year) "{}/team/{team_key}/events/{year}/statuses"
 }
 endpoint!{
     TeamEP: (TeamKey) => Team
     where (team_key) "{}/team/{team_key}"
 }
+endpoint!{
+    TeamMatchesByYearEP: (TeamKey, Year) => Vec<Match>
+    where (team_key, year) "{}/team/{team_key}/matches/{year}"
+    then (matches, ctx) {
+        // Unplayed matches are left out, as they are the ones whose results are about to change
+        for m in matches.iter().filter(|m| m.is_played()) {
+            ctx.endpoints.matches.matches.seed((m.key.clone(),), Arc::new(m.clone()));
+        }
+    }
+}
+endpoint!{
+    TeamEventsByYearEP: (TeamKey, Year) => Vec<Event>
+    where (team_key, year) "{}/team/{team_key}/events/{year}"
+    then (events, ctx) {
+        for event in events.iter() {
+            ctx.endpoints.event.event.seed((event.simple.key.clone(),), Arc::new(event.clone()));
+        }
+    }
+}
 
 endpoint!{EventEP: (EventKey) => Event where (event_key) "{}/event/{event_key}"}
-endpoint!{SimpleEventEP: (EventKey) => Event where (event_key) "{}/event/{event_key}/simple"}
+endpoint!{SimpleEventEP: (EventKey) => SimpleEvent where (event_key) "{}/event/{event_key}/simple"}
 endpoint!{EliminationAlliancesEP: (EventKey) => Vec<EliminationAlliance> where (event_key) "{}/event/{event_key}/alliances"}
-endpoint!{EventOPRsEP: (EventKey) => EventOPRs where (event_key) "{}/event/{event_key}/oprs"}
-endpoint!{EventDistrictPointsEP: (EventKey) => EventDistrictPoints where (event_key) "{}/event/{event_key}/district_points"}
+endpoint!{EventOPRsEP: (EventKey) => Option<EventOPRs> where (event_key) "{}/event/{event_key}/oprs"}
+endpoint!{EventRankingsEP: (EventKey) => Option<EventRankings> where (event_key) "{}/event/{event_key}/rankings"}
+endpoint!{EventDistrictPointsEP: (EventKey) => Option<EventDistrictPoints> where (event_key) "{}/event/{event_key}/district_points"}
+endpoint!{EventAwardsEP: (EventKey) => Vec<Award> where (event_key) "{}/event/{event_key}/awards"}
+endpoint!{
+    EventTeamsEP: (EventKey) => Vec<Team>
+    where (event_key) "{}/event/{event_key}/teams"
+    then (teams, ctx) {
+        for team in teams.iter() {
+            ctx.endpoints.team.team.seed((team.simple.key.clone(),), Arc::new(team.clone()));
+        }
+    }
+}
+endpoint!{EventSimpleTeamsEP: (EventKey) => Vec<SimpleTeam> where (event_key) "{}/event/{event_key}/teams/simple"}
 endpoint!{EventTeamKeysEP: (EventKey) => Vec<TeamKey> where (event_key) "{}/event/{event_key}/teams/keys"}
-endpoint!{EventTeamStatusesEP: (EventKey) => HashMap<EventKey, TeamEventStatus> where (event_key) "{}/event/{event_key}/teams/statuses"}
+endpoint!{EventTeamStatusesEP: (EventKey) => HashMap<TeamKey, Option<TeamEventStatus>> where (event_key) "{}/event/{event_key}/teams/statuses"}
 endpoint!{EventMatchesEP: (EventKey) => Vec<Match> where (event_key) "{}/event/{event_key}/matches"}
 endpoint!{EventMatchKeysEP: (EventKey) => Vec<MatchKey> where (event_key) "{}/event/{event_key}/matches/keys"}
 
+endpoint!{
+    EventsByYearEP: (Year) => Vec<Event>
+    where (year) "{}/events/{year}"
+    then (events, ctx) {
+        for event in events.iter() {
+            ctx.endpoints.event.event.seed((event.simple.key.clone(),), Arc::new(event.clone()));
+        }
+    }
+}
+
 endpoint!{MatchEP: (MatchKey) => Match where (match_key) "{}/match/{match_key}"}
 
 
-/// Get the given path from the given endpoint, utilizing the cache
+/// Get the given path from the given endpoint, utilizing the cache. Returns the value and
+/// whether it was read from a new response body
 async fn get_ep<T: EndPoint + 'static>(
     path: String,
     params: T::Params,
     cache: &Cache<T::Params, EndPointCacheEntry<T::Value>>,
     ctx: &Context,
-) -> Result<T::Value, Error> 
+) -> Result<(T::Value, bool), Error> 
 where 
     T::Params: std::hash::Hash + std::cmp::Eq + Send + Sync,
     T::Value: Clone + Send + Sync + DeserializeOwned {
     let cached = cache.get(&params);
-    let mut request = ctx
-        .client
-        .request(
-            Method::GET,
-            path,
-        );
+    if let Some(entry) = cached.as_ref().filter(|entry| entry.is_fresh()) {
+        return Ok((entry.val.clone(), false))
+    }
 
-    if let Some(ref cached) = cached {
-        request = request.insert_header((IF_NONE_MATCH, cached.etag.clone()));
+    let etag = cached.as_ref().and_then(|entry| entry.etag.as_deref());
+    match (get_if_changed::<T>(path, etag, ctx).await?, cached) {
+        (Some((val, etag)), _) => {
+            cache.insert(params, EndPointCacheEntry { val: val.clone(), etag, fresh_until: None });
+            Ok((val, true))
+        },
+        (None, Some(cached)) => Ok((cached.val, false)),
+        (None, None) => Err(Error::BadResponse(StatusCode::NOT_MODIFIED)),
     }
-    
+}
+
+/// Get the given path unless the resource still has the given ETag, bypassing the cache
+async fn get_if_changed<T: EndPoint>(
+    path: String,
+    etag: Option<&str>,
+    ctx: &Context,
+) -> Result<Option<(T::Value, Option<String>)>, Error>
+where
+    T::Value: DeserializeOwned {
+    let mut request = ctx.client.request(Method::GET, path);
+    if let Some(etag) = etag {
+        request = request.insert_header((IF_NONE_MATCH, etag.to_owned()));
+    }
+
     let mut response = request.send().await?;
-    match (response.status(), cached) {
-        (StatusCode::NOT_MODIFIED, Some(cached)) => Ok(cached.val),
-        (code, _) if code.is_success() => {
+    match response.status() {
+        StatusCode::NOT_MODIFIED => Ok(None),
+        code if code.is_success() => {
             let etag = response
                 .headers()
                 .get(ETAG)
-                .map(|v| v
-                    .to_str()
-                    .map(str::to_owned)
-                );
-            let val = response.json::<T::Value>().await?;
-            if let Some(etag) = etag {
-                cache.insert(
-                    params,
-                    EndPointCacheEntry {
-                        val: val.clone(),
-                        etag: etag?
-                    }
-                );
-            }
-
-            Ok(val)
+                .map(|v| v.to_str().map(str::to_owned))
+                .transpose()?;
+            let body = response.body().limit(MAX_RESPONSE_SIZE).await?;
+            let val = with_strictness(ctx.strict, || serde_json::from_slice::<T::Value>(&body))?;
+            Ok(Some((val, etag)))
         },
-        (code, _) => Err(Error::BadResponse(code)),
+        code => Err(Error::BadResponse(code)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ctx::mock::MockApi, model::{id::Key, matches::test_util::{fixture, match_with}}};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn seeded_entries() {
+        let json = include_str!("../../fixtures/team_frc1280.json");
+        let team: Team = serde_json::from_str(json).unwrap();
+        let key = team.simple.key.clone();
+        let api = MockApi::default();
+        api.set("event/2023cafr/teams", format!("[{}]", json), "\"teams\"");
+        api.set("team/frc1280", json, "\"team\"");
+        let ctx = Context::authenticate("key").unwrap().with_base_url(api.serve());
+        let event: EventKey = "2023cafr".parse().unwrap();
+
+        // Teams from the event roster are served without a request until the seed expires
+        ctx.endpoints.event.teams.get((event.clone(),), &ctx).await.unwrap();
+        assert_eq!((&key).upgrade(&ctx).await.unwrap().simple.key, key);
+        assert_eq!(api.take_requests(), ["event/2023cafr/teams"]);
+
+        let cache = &ctx.endpoints.team.team.cache;
+        let mut seeded = cache.get(&(key.clone(),)).unwrap();
+        seeded.fresh_until = Some(Instant::now());
+        cache.insert((key.clone(),), seeded);
+        (&key).upgrade(&ctx).await.unwrap();
+        (&key).upgrade(&ctx).await.unwrap();
+        assert_eq!(api.take_requests(), ["team/frc1280", "team/frc1280"]);
+        assert_eq!(cache.get(&(key.clone(),)).unwrap().etag.as_deref(), Some("\"team\""));
+
+        // An unchanged roster does not reseed the teams
+        ctx.endpoints.event.teams.get((event,), &ctx).await.unwrap();
+        assert_eq!(api.take_requests(), ["event/2023cafr/teams"]);
+        let entry = cache.get(&(key,)).unwrap();
+        assert_eq!(entry.etag.as_deref(), Some("\"team\""));
+        assert!(!entry.is_fresh());
+    }
+
+    #[actix_rt::test]
+    async fn changed_without_etag() {
+        let json = include_str!("../../fixtures/team_frc1280.json");
+        let team: Team = serde_json::from_str(json).unwrap();
+        let key = team.simple.key.clone();
+        let api = MockApi::default();
+        api.set("team/frc1280", json, "");
+        let ctx = Context::authenticate("key").unwrap().with_base_url(api.serve());
+        let endpoint = &ctx.endpoints.team.team;
+
+        // A value fetched without an ETag replaces a seeded value that is still fresh
+        let mut seeded = team.clone();
+        seeded.simple.nickname = None;
+        endpoint.seed((key.clone(),), Arc::new(seeded));
+        let (val, etag) = endpoint.get_if_changed((key.clone(),), None, &ctx).await.unwrap().unwrap();
+        assert_eq!((val.simple.nickname.clone(), etag), (team.simple.nickname.clone(), None));
+        let entry = endpoint.cache.get(&(key.clone(),)).unwrap();
+        assert_eq!((entry.etag, entry.fresh_until), (None, None));
+        assert_eq!(endpoint.get((key,), &ctx).await.unwrap().simple.nickname, team.simple.nickname);
+        assert_eq!(api.take_requests(), ["team/frc1280", "team/frc1280"]);
+    }
+
+    #[test]
+    fn invalidate_event() {
+        let endpoints = EndPoints::default();
+        let m = fixture();
+        let other_match = match_with("2023cafr2_qm1", &m.alliances.red.team_keys, &m.alliances.blue.team_keys, Some((47, 61)));
+        let other = other_match.key.clone();
+        endpoints.matches.matches.seed((m.key.clone(),), Arc::new(m.clone()));
+        endpoints.matches.matches.seed((other.clone(),), Arc::new(other_match));
+        endpoints.event.matches.seed((m.event_key.clone(),), Arc::new(vec![m.clone()]));
+
+        let year = m.event_key.year();
+        let (attending, absent): (TeamKey, TeamKey) = ("frc1280".parse().unwrap(), "frc254".parse().unwrap());
+        let seed_team_matches = || for team in [&attending, &absent] {
+            endpoints.team.matches_by_year.seed((team.clone(), year), Arc::new(Vec::new()));
+        };
+        let team_matches_cached = |team: &TeamKey| endpoints.team.matches_by_year.cache.get(&(team.clone(), year)).is_some();
+        seed_team_matches();
+        endpoints.event.team_keys.seed((m.event_key.clone(),), Arc::new(vec![attending.clone()]));
+
+        endpoints.invalidate_event(&m.event_key);
+        assert!(endpoints.matches.matches.cache.get(&(m.key.clone(),)).is_none());
+        assert!(endpoints.event.matches.cache.get(&(m.event_key.clone(),)).is_none());
+        assert!(endpoints.event.team_keys.cache.get(&(m.event_key.clone(),)).is_none());
+        assert!(endpoints.matches.matches.cache.get(&(other,)).is_some());
+        assert!(!team_matches_cached(&attending));
+        assert!(team_matches_cached(&absent));
+
+        // Without a cached roster, every team in the year is affected
+        seed_team_matches();
+        endpoints.invalidate_event(&m.event_key);
+        assert!(!team_matches_cached(&attending));
+        assert!(!team_matches_cached(&absent));
+    }
+
+    #[test]
+    fn invalidate_team() {
+        let endpoints = EndPoints::default();
+        let team: Team = serde_json::from_str(include_str!("../../fixtures/team_frc1280.json")).unwrap();
+        let key = team.simple.key.clone();
+        let other: TeamKey = "frc254".parse().unwrap();
+        let year = Year::new(2023).unwrap();
+        for team in [&key, &other] {
+            endpoints.team.matches_by_year.seed((team.clone(), year), Arc::new(Vec::new()));
+            endpoints.team.events_by_year.seed((team.clone(), year), Arc::new(Vec::new()));
+            endpoints.team.event_statuses_by_year.seed((team.clone(), year), Arc::new(HashMap::new()));
+        }
+        endpoints.team.team.seed((key.clone(),), Arc::new(team));
+
+        endpoints.invalidate_team(&key);
+        assert!(endpoints.team.team.cache.get(&(key.clone(),)).is_none());
+        for (team, cached) in [(&key, false), (&other, true)] {
+            let params = (team.clone(), year);
+            assert_eq!(endpoints.team.matches_by_year.cache.get(&params).is_some(), cached);
+            assert_eq!(endpoints.team.events_by_year.cache.get(&params).is_some(), cached);
+            assert_eq!(endpoints.team.event_statuses_by_year.cache.get(&params).is_some(), cached);
+        }
+    }
+
+    #[test]
+    fn invalidate_match() {
+        let endpoints = EndPoints::default();
+        let m = fixture();
+        let next = match_with("2023cafr_qm2", &m.alliances.red.team_keys, &m.alliances.blue.team_keys, Some((47, 61)));
+        endpoints.matches.matches.seed((m.key.clone(),), Arc::new(m.clone()));
+        endpoints.matches.matches.seed((next.key.clone(),), Arc::new(next.clone()));
+
+        endpoints.invalidate_match(&m.key);
+        assert!(endpoints.matches.matches.cache.get(&(m.key,)).is_none());
+        assert!(endpoints.matches.matches.cache.get(&(next.key,)).is_some());
     }
 }
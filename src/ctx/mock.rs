@@ -0,0 +1,96 @@
+//! Local HTTP server standing in for the API in tests
+
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use actix_web::{http::{header::{HeaderMap, ETAG, IF_NONE_MATCH}, Method}, web, App, HttpRequest, HttpResponse, HttpServer};
+use url::Url;
+
+/// A request received by a [MockApi]
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    pub method: Method,
+    /// Path of the request relative to the API root
+    pub path: String,
+    pub headers: HeaderMap,
+    pub body: web::Bytes,
+}
+
+impl MockRequest {
+    /// Get the value of a header, or `None` if it is missing or not valid text
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+/// Resources served in place of the API, which answer requests carrying their current ETag with
+/// `304 Not Modified`. Resources with an empty ETag are served without one. Requests other than
+/// `GET` are answered with an empty `200 OK`
+#[derive(Clone, Default)]
+pub struct MockApi {
+    /// Body and ETag of each resource, keyed by its path relative to the API root
+    resources: Arc<Mutex<HashMap<String, (String, String)>>>,
+    /// Every request received, in the order they were received
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockApi {
+    /// Serve the given body and ETag at a path relative to the API root
+    pub fn set(&self, path: &str, body: impl Into<String>, etag: &str) {
+        self.resources.lock().unwrap().insert(path.to_owned(), (body.into(), etag.to_owned()));
+    }
+
+    /// Get the paths requested since the last call, in the order they were received
+    pub fn take_requests(&self) -> Vec<String> {
+        self.take_received().into_iter().map(|request| request.path).collect()
+    }
+
+    /// Get the requests received since the last call, in the order they were received
+    pub fn take_received(&self) -> Vec<MockRequest> {
+        std::mem::take(&mut self.requests.lock().unwrap())
+    }
+
+    /// Start serving the resources on a local port, returning the base URL to use for the API
+    pub fn serve(&self) -> Url {
+        self.serve_at("api/v3/")
+    }
+
+    /// Start serving the resources on a local port under the given root path, which should end
+    /// with a slash, returning the URL of the root
+    pub fn serve_at(&self, root: &str) -> Url {
+        let api = self.clone();
+        let prefix = format!("/{}", root);
+        let server = HttpServer::new(move || {
+            let (api, prefix) = (api.clone(), prefix.clone());
+            App::new().default_service(web::to(move |req: HttpRequest, body: web::Bytes| {
+                let path = req.path().trim_start_matches(prefix.as_str()).trim_start_matches('/').to_owned();
+                api.requests.lock().unwrap().push(MockRequest {
+                    method: req.method().clone(),
+                    path: path.clone(),
+                    headers: req.headers().clone(),
+                    body,
+                });
+                let response = match api.resources.lock().unwrap().get(&path) {
+                    _ if req.method() != Method::GET => HttpResponse::Ok().finish(),
+                    Some((_, etag)) if !etag.is_empty() && req.headers().get(IF_NONE_MATCH).is_some_and(|v| v == etag.as_str()) => {
+                        HttpResponse::NotModified().finish()
+                    },
+                    Some((body, etag)) => {
+                        let mut response = HttpResponse::Ok();
+                        if !etag.is_empty() {
+                            response.insert_header((ETAG, etag.as_str()));
+                        }
+                        response.content_type("application/json").body(body.clone())
+                    },
+                    None => HttpResponse::NotFound().finish(),
+                };
+                async { response }
+            }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_rt::spawn(server.run());
+        Url::parse(&format!("http://{}/{}", addr, root)).unwrap()
+    }
+}
@@ -1,38 +1,53 @@
 pub mod endpoints;
+#[cfg(test)]
+pub(crate) mod mock;
 
-use std::{time::Duration, sync::Arc};
+use std::time::Duration;
 
 
 use awc::{Client, http::header::HeaderName, ClientBuilder, error::HttpError};
+use url::Url;
 
-use self::endpoints::EndPoints;
+use self::endpoints::{EndPoints, BASE_ENDPOINT};
 
 
 
 /// Context for interacting with the API, containing all state needed to make requests over the
 /// internet
 pub struct Context {
-    pub(crate) client: Arc<Client>,
+    pub(crate) client: Client,
+    pub(crate) base: Url,
     pub endpoints: EndPoints,
+    /// If set, responses containing enum values that are not known to this crate fail to
+    /// deserialize instead of being kept as `Unknown` variants
+    pub strict: bool,
 }
 
 impl Context {
     /// Create a new context with the given API key
     pub fn authenticate(tba_auth_key: impl AsRef<str>) -> Result<Self, HttpError> {
         Ok(Context {
-            client: Arc::new(ClientBuilder::new()
+            client: ClientBuilder::new()
                 .add_default_header(
                     (
-                        HeaderName::from_static("X-TBA-Auth-Key"),
+                        HeaderName::from_static("x-tba-auth-key"),
                         tba_auth_key
                             .as_ref()
                             .trim()
                     )
                 )
                 .timeout(Duration::from_secs(30))
-                .finish()
-            ),
+                .finish(),
+            base: Url::parse(BASE_ENDPOINT).expect("API base URL is valid"),
             endpoints: Default::default(),
+            strict: false,
         })
     }
+
+    /// Send requests to the given base URL instead of the TBA API, e.g. a local server used for
+    /// testing. The URL should end with a trailing slash
+    pub fn with_base_url(mut self, base: Url) -> Self {
+        self.base = base;
+        self
+    }
 }
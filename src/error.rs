@@ -8,8 +8,10 @@ pub enum Error {
     Http(#[from] awc::error::SendRequestError),
     #[error("Failed to convert HTTP header value to string: {0}")]
     ToStr(#[from] awc::http::header::ToStrError),
-    #[error("Failed to deserialize JSON response: {0}")]
-    JSON(#[from] awc::error::JsonPayloadError),
+    #[error("Failed to read HTTP response body: {0}")]
+    Payload(#[from] awc::error::PayloadError),
+    #[error("Failed to serialize or deserialize JSON: {0}")]
+    SerdeJSON(#[from] serde_json::Error),
     #[error("Invalid HTTP header: {0}")]
     InvalidHeader(#[from] awc::http::header::InvalidHeaderName),
     #[error("Failed to parse URL: {0}")]
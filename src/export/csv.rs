@@ -0,0 +1,541 @@
+//! CSV tables of matches, rankings, OPRs and team rosters, with configurable columns, and
+//! import of the tables that can be read back without losing data: the match, OPR and team
+//! tables. The team-match table is not read back, as it is a view of the match table without
+//! match times. Neither is the ranking table, which does not record which of its columns are
+//! sort orders and which are extra stats
+
+use std::{borrow::Cow, collections::HashMap, fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Utc};
+
+use crate::model::{
+    event::{EventKey, EventOPRs, EventRankings, PlayoffLevel, TeamEventStatusRankRanking},
+    id::TeamNumber,
+    matches::{AllianceColor, Match, MatchAlliance, MatchAlliances, MatchKey, MatchWinner},
+    team::{SimpleTeam, Team, TeamKey},
+};
+
+/// Errors that can occur while reading a CSV table
+#[derive(Debug, thiserror::Error)]
+pub enum CsvError {
+    #[error("Quoted field starting on line {0} is never closed")]
+    UnclosedQuote(usize),
+    #[error("Table has no '{0}' column")]
+    MissingColumn(String),
+    #[error("Line {line} has a malformed value '{value}' in column '{column}'")]
+    Value { line: usize, column: String, value: String },
+}
+
+/// A named column of a table, which formats one field of each row
+pub struct Column<T> {
+    pub name: Cow<'static, str>,
+    value: Box<dyn Fn(&T) -> String>,
+}
+
+impl<T> Column<T> {
+    /// Create a column with the given header that formats each row using `value`
+    pub fn new(name: impl Into<Cow<'static, str>>, value: impl Fn(&T) -> String + 'static) -> Self {
+        Self { name: name.into(), value: Box::new(value) }
+    }
+}
+
+/// Set of columns used to write rows of `T` as CSV
+pub struct Table<T> {
+    columns: Vec<Column<T>>,
+}
+
+impl<T> Table<T> {
+    /// Create a table with the given columns, in order
+    pub fn new(columns: Vec<Column<T>>) -> Self {
+        Self { columns }
+    }
+
+    /// Add a column to the end of this table
+    pub fn with(mut self, column: Column<T>) -> Self {
+        self.columns.push(column);
+        self
+    }
+
+    /// Keep only the columns with the given names, in the order they are given. Names without
+    /// a column are ignored
+    pub fn only(mut self, names: &[&str]) -> Self {
+        let mut columns = Vec::with_capacity(names.len());
+        for name in names {
+            if let Some(i) = self.columns.iter().position(|column| column.name == *name) {
+                columns.push(self.columns.remove(i));
+            }
+        }
+        self.columns = columns;
+        self
+    }
+
+    /// Remove the columns with the given names
+    pub fn without(mut self, names: &[&str]) -> Self {
+        self.columns.retain(|column| !names.contains(&column.name.as_ref()));
+        self
+    }
+
+    /// Iterate over the names of this table's columns
+    pub fn headers(&self) -> impl Iterator<Item = &str> {
+        self.columns.iter().map(|column| column.name.as_ref())
+    }
+
+    /// Write a header line followed by a line for each row
+    pub fn write<'a>(&self, rows: impl IntoIterator<Item = &'a T>, out: &mut impl std::io::Write) -> std::io::Result<()>
+    where
+        T: 'a,
+    {
+        out.write_all(self.to_csv(rows).as_bytes())
+    }
+
+    /// Format a header line followed by a line for each row
+    pub fn to_csv<'a>(&self, rows: impl IntoIterator<Item = &'a T>) -> String
+    where
+        T: 'a,
+    {
+        let mut out = String::new();
+        write_line(&mut out, self.headers());
+        for row in rows {
+            write_line(&mut out, self.columns.iter().map(|column| (column.value)(row)));
+        }
+        out
+    }
+}
+
+/// Append a line of fields to a table, quoting fields that contain separators or quotes
+fn write_line<S: AsRef<str>>(out: &mut String, fields: impl Iterator<Item = S>) {
+    for (i, field) in fields.enumerate() {
+        let field = field.as_ref();
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+/// Format an optional value as an empty field when it is missing
+fn opt<T: Display>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// One row of each match for every team that played in it
+#[derive(Clone, Debug)]
+pub struct TeamMatch<'a> {
+    pub match_: &'a Match,
+    pub team: &'a TeamKey,
+    pub color: AllianceColor,
+    /// Driver station of the team, from 1
+    pub station: usize,
+}
+
+impl<'a> TeamMatch<'a> {
+    /// Get a row for every team in each of the given matches
+    pub fn rows(matches: impl IntoIterator<Item = &'a Match>) -> Vec<Self> {
+        matches
+            .into_iter()
+            .flat_map(|m| [AllianceColor::Red, AllianceColor::Blue]
+                .into_iter()
+                .flat_map(move |color| m.alliances.get(color).team_keys.iter().enumerate().map(move |(i, team)| TeamMatch {
+                    match_: m,
+                    team,
+                    color,
+                    station: i + 1,
+                }))
+            )
+            .collect()
+    }
+
+    fn alliances(&self) -> (&'a MatchAlliance, &'a MatchAlliance) {
+        (self.match_.alliances.get(self.color), self.match_.alliances.get(self.color.opponent()))
+    }
+}
+
+/// OPR, DPR and CCWM of a single team
+#[derive(Clone, Debug, PartialEq)]
+pub struct OprRow {
+    pub team: TeamKey,
+    pub opr: Option<f64>,
+    pub dpr: Option<f64>,
+    pub ccwm: Option<f64>,
+}
+
+impl OprRow {
+    /// Get a row for every team with any rating, in order of team number
+    pub fn rows(oprs: &EventOPRs) -> Vec<Self> {
+        let mut teams = oprs.oprs.keys().chain(oprs.dprs.keys()).chain(oprs.ccwms.keys()).collect::<Vec<_>>();
+        teams.sort_by_key(|team| (team.number(), team.as_ref().to_owned()));
+        teams.dedup();
+        teams
+            .into_iter()
+            .map(|team| Self {
+                team: team.clone(),
+                opr: oprs.oprs.get(team).copied(),
+                dpr: oprs.dprs.get(team).copied(),
+                ccwm: oprs.ccwms.get(team).copied(),
+            })
+            .collect()
+    }
+}
+
+/// Columns of one row per match: keys, times, the teams in each driver station, scores and the
+/// winner. Times are formatted as RFC 3339
+pub fn match_table() -> Table<Match> {
+    let mut columns = vec![
+        Column::new("key", |m: &Match| m.key.as_ref().to_owned()),
+        Column::new("event_key", |m: &Match| m.event_key.as_ref().to_owned()),
+        Column::new("comp_level", |m: &Match| m.comp_level.as_str().to_owned()),
+        Column::new("set_number", |m: &Match| m.set_number.to_string()),
+        Column::new("match_number", |m: &Match| m.match_number.to_string()),
+        Column::new("time", |m: &Match| opt(m.time.map(|time| time.to_rfc3339()))),
+        Column::new("actual_time", |m: &Match| opt(m.actual_time.map(|time| time.to_rfc3339()))),
+    ];
+    for color in [AllianceColor::Red, AllianceColor::Blue] {
+        for station in 0..3 {
+            let name = format!("{}{}", color.as_str(), station + 1);
+            columns.push(Column::new(name, move |m: &Match| {
+                opt(m.alliances.get(color).team_keys.get(station).map(|team| team.as_ref()))
+            }));
+        }
+    }
+    columns.push(Column::new("red_score", |m: &Match| opt(m.alliances.red.score)));
+    columns.push(Column::new("blue_score", |m: &Match| opt(m.alliances.blue.score)));
+    columns.push(Column::new("winning_alliance", |m: &Match| m.winning_alliance.as_str().to_owned()));
+    Table::new(columns)
+}
+
+/// Columns of one row per team per match, with the scores of the team's alliance and its
+/// opponent, and the result for the team as `W`, `L` or `T`, left empty if the match has not
+/// been played
+pub fn team_match_table<'a>() -> Table<TeamMatch<'a>> {
+    Table::new(vec![
+        Column::new("match_key", |row: &TeamMatch| row.match_.key.as_ref().to_owned()),
+        Column::new("team_key", |row: &TeamMatch| row.team.as_ref().to_owned()),
+        Column::new("color", |row: &TeamMatch| row.color.as_str().to_owned()),
+        Column::new("station", |row: &TeamMatch| row.station.to_string()),
+        Column::new("score", |row: &TeamMatch| opt(row.alliances().0.score)),
+        Column::new("opponent_score", |row: &TeamMatch| opt(row.alliances().1.score)),
+        Column::new("result", |row: &TeamMatch| match &row.match_.winning_alliance {
+            _ if !row.match_.is_played() => String::new(),
+            MatchWinner::Tie => "T".to_owned(),
+            winner if *winner == row.color.into() => "W".to_owned(),
+            _ => "L".to_owned(),
+        }),
+        Column::new("surrogate", |row: &TeamMatch| row.alliances().0.surrogate_team_keys.contains(row.team).to_string()),
+        Column::new("dq", |row: &TeamMatch| row.alliances().0.dq_team_keys.contains(row.team).to_string()),
+    ])
+}
+
+/// Columns of one row per team with its OPR, DPR and CCWM
+pub fn opr_table() -> Table<OprRow> {
+    Table::new(vec![
+        Column::new("team_key", |row: &OprRow| row.team.as_ref().to_owned()),
+        Column::new("opr", |row: &OprRow| opt(row.opr)),
+        Column::new("dpr", |row: &OprRow| opt(row.dpr)),
+        Column::new("ccwm", |row: &OprRow| opt(row.ccwm)),
+    ])
+}
+
+/// Columns of one row per ranked team, with a column named after each of the event's sort
+/// orders and extra stats
+pub fn ranking_table(rankings: &EventRankings) -> Table<TeamEventStatusRankRanking> {
+    let mut table = Table::new(vec![
+        Column::new("rank", |r: &TeamEventStatusRankRanking| opt(r.rank)),
+        Column::new("team_key", |r: &TeamEventStatusRankRanking| opt(r.team_key.as_ref().map(|team| team.as_ref()))),
+        Column::new("wins", |r: &TeamEventStatusRankRanking| opt(r.record.as_ref().and_then(|record| record.wins))),
+        Column::new("losses", |r: &TeamEventStatusRankRanking| opt(r.record.as_ref().and_then(|record| record.losses))),
+        Column::new("ties", |r: &TeamEventStatusRankRanking| opt(r.record.as_ref().and_then(|record| record.ties))),
+        Column::new("matches_played", |r: &TeamEventStatusRankRanking| opt(r.matches_played)),
+        Column::new("dq", |r: &TeamEventStatusRankRanking| opt(r.dq)),
+    ]);
+    type Values = fn(&TeamEventStatusRankRanking) -> Option<&Vec<f64>>;
+    let named: [(_, Values); 2] = [
+        (Some(&rankings.sort_order_info), |r| r.sort_orders.as_ref()),
+        (rankings.extra_stats_info.as_ref(), |r| r.extra_stats.as_ref()),
+    ];
+    for (info, values) in named {
+        for (i, info) in info.into_iter().flatten().enumerate() {
+            let name = info.name.clone().unwrap_or_else(|| format!("sort_order_{}", i + 1));
+            table = table.with(Column::new(name, move |r: &TeamEventStatusRankRanking| opt(values(r).and_then(|values| values.get(i).copied()))));
+        }
+    }
+    table
+}
+
+/// Columns of one row per team with the fields of a [SimpleTeam]
+pub fn simple_team_table() -> Table<SimpleTeam> {
+    Table::new(vec![
+        Column::new("key", |t: &SimpleTeam| t.key.as_ref().to_owned()),
+        Column::new("team_number", |t: &SimpleTeam| t.team_number.to_string()),
+        Column::new("nickname", |t: &SimpleTeam| opt(t.nickname.as_ref())),
+        Column::new("name", |t: &SimpleTeam| t.name.clone()),
+        Column::new("city", |t: &SimpleTeam| opt(t.city.as_ref())),
+        Column::new("state_prov", |t: &SimpleTeam| opt(t.state_prov.as_ref())),
+        Column::new("country", |t: &SimpleTeam| opt(t.country.as_ref())),
+    ])
+}
+
+/// Columns of one row per team in a roster, with the fields of its [SimpleTeam] followed by
+/// its rookie year, school, website and motto
+pub fn team_table() -> Table<Team> {
+    let mut table = Table::new(simple_team_table()
+        .columns
+        .into_iter()
+        .map(|column| {
+            let value = column.value;
+            Column::new(column.name, move |t: &Team| value(&t.simple))
+        })
+        .collect());
+    for column in [
+        Column::new("rookie_year", |t: &Team| opt(t.rookie_year)),
+        Column::new("school_name", |t: &Team| opt(t.school_name.as_ref())),
+        Column::new("website", |t: &Team| opt(t.website.as_ref())),
+        Column::new("motto", |t: &Team| opt(t.motto.as_ref())),
+    ] {
+        table = table.with(column);
+    }
+    table
+}
+
+/// Split CSV text into records of fields, following RFC 4180, along with the line each record
+/// starts on. Quoted fields may span several lines
+fn parse(input: &str) -> Result<Vec<(usize, Vec<String>)>, CsvError> {
+    let mut lines = Vec::new();
+    let (mut line, mut field) = (Vec::new(), String::new());
+    let (mut quoted, mut quote_start) = (false, 0);
+    let (mut line_number, mut line_start) = (1, 1);
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => {
+                line_number += usize::from(c == '\n');
+                field.push(c);
+            }
+            (false, '"') if field.is_empty() => {
+                quoted = true;
+                quote_start = line_number;
+            }
+            (false, ',') => line.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => (),
+            (false, '\n') => {
+                line.push(std::mem::take(&mut field));
+                lines.push((line_start, std::mem::take(&mut line)));
+                line_number += 1;
+                line_start = line_number;
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(CsvError::UnclosedQuote(quote_start))
+    }
+    if !field.is_empty() || !line.is_empty() {
+        line.push(field);
+        lines.push((line_start, line));
+    }
+    Ok(lines)
+}
+
+/// Records of a parsed table after its header, with fields looked up by column name
+struct Rows {
+    columns: HashMap<String, usize>,
+    /// Non-empty records with the line each starts on
+    lines: Vec<(usize, Vec<String>)>,
+}
+
+impl Rows {
+    fn parse(input: &str) -> Result<Self, CsvError> {
+        let mut lines = parse(input)?.into_iter();
+        let columns = lines.next().unwrap_or_default().1.into_iter().enumerate().map(|(i, name)| (name, i)).collect();
+        Ok(Self { columns, lines: lines.filter(|(_, line)| line.iter().any(|field| !field.is_empty())).collect() })
+    }
+
+    /// Get the value of a column in each record, as `(line number, value)`
+    fn column<'a>(&'a self, name: &str) -> Result<impl Iterator<Item = (usize, &'a str)> + 'a, CsvError> {
+        let i = *self.columns.get(name).ok_or_else(|| CsvError::MissingColumn(name.to_owned()))?;
+        Ok(self.lines.iter().map(move |(line, fields)| (*line, fields.get(i).map_or("", String::as_str))))
+    }
+
+    /// Parse every value of a column, with empty values as `None`
+    fn parsed<T: FromStr>(&self, name: &str) -> Result<Vec<Option<T>>, CsvError> {
+        self.column(name)?
+            .map(|(line, value)| match value {
+                "" => Ok(None),
+                value => value.parse().map(Some).map_err(|_| CsvError::Value {
+                    line,
+                    column: name.to_owned(),
+                    value: value.to_owned(),
+                }),
+            })
+            .collect()
+    }
+
+    /// Parse every value of a column that must not be empty
+    fn required<T: FromStr>(&self, name: &str) -> Result<Vec<T>, CsvError> {
+        self.parsed(name)?
+            .into_iter()
+            .zip(self.column(name)?)
+            .map(|(value, (line, _))| value.ok_or_else(|| CsvError::Value { line, column: name.to_owned(), value: String::new() }))
+            .collect()
+    }
+
+    fn strings(&self, name: &str) -> Result<Vec<Option<String>>, CsvError> {
+        Ok(self.column(name)?.map(|(_, value)| (!value.is_empty()).then(|| value.to_owned())).collect())
+    }
+}
+
+/// Read matches from a table written with the columns of [match_table]. Fields that the table
+/// does not hold, such as score breakdowns, videos, and surrogate and disqualified teams, are
+/// left empty
+pub fn read_matches(input: &str) -> Result<Vec<Match>, CsvError> {
+    let rows = Rows::parse(input)?;
+    let keys = rows.required::<MatchKey>("key")?;
+    let event_keys = rows.required::<EventKey>("event_key")?;
+    let levels = rows.required::<PlayoffLevel>("comp_level")?;
+    let sets = rows.required::<u32>("set_number")?;
+    let numbers = rows.required::<u32>("match_number")?;
+    let times = rows.parsed::<DateTime<Utc>>("time")?;
+    let actual_times = rows.parsed::<DateTime<Utc>>("actual_time")?;
+    let winners = rows.parsed::<MatchWinner>("winning_alliance")?;
+    let mut alliances = Vec::new();
+    for color in [AllianceColor::Red, AllianceColor::Blue] {
+        let stations = (1..=3)
+            .map(|station| rows.parsed::<TeamKey>(&format!("{}{}", color.as_str(), station)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let scores = rows.parsed::<i32>(&format!("{}_score", color.as_str()))?;
+        alliances.push((stations, scores));
+    }
+
+    let alliance = |(stations, scores): &(Vec<Vec<Option<TeamKey>>>, Vec<Option<i32>>), i: usize| MatchAlliance {
+        score: scores[i],
+        team_keys: stations.iter().filter_map(|teams| teams[i].clone()).collect(),
+        surrogate_team_keys: Vec::new(),
+        dq_team_keys: Vec::new(),
+    };
+    Ok((0..keys.len())
+        .map(|i| Match {
+            key: keys[i].clone(),
+            comp_level: levels[i].clone(),
+            set_number: sets[i],
+            match_number: numbers[i],
+            alliances: MatchAlliances { red: alliance(&alliances[0], i), blue: alliance(&alliances[1], i) },
+            winning_alliance: winners[i].clone().unwrap_or(MatchWinner::Tie),
+            event_key: event_keys[i].clone(),
+            time: times[i],
+            actual_time: actual_times[i],
+            predicted_time: None,
+            post_result_time: None,
+            score_breakdown: None,
+            videos: Vec::new(),
+        })
+        .collect())
+}
+
+/// Read the OPRs of an event from a table written with the columns of [opr_table]
+pub fn read_oprs(input: &str) -> Result<EventOPRs, CsvError> {
+    let rows = Rows::parse(input)?;
+    let teams = rows.required::<TeamKey>("team_key")?;
+    let mut oprs = EventOPRs { oprs: HashMap::new(), dprs: HashMap::new(), ccwms: HashMap::new() };
+    for (name, map) in [("opr", &mut oprs.oprs), ("dpr", &mut oprs.dprs), ("ccwm", &mut oprs.ccwms)] {
+        for (team, value) in teams.iter().zip(rows.parsed::<f64>(name)?) {
+            if let Some(value) = value {
+                map.insert(team.clone(), value);
+            }
+        }
+    }
+    Ok(oprs)
+}
+
+/// Read teams from a table written with the columns of [simple_team_table] or [team_table].
+/// Empty optional fields are read as `None`
+pub fn read_simple_teams(input: &str) -> Result<Vec<SimpleTeam>, CsvError> {
+    let rows = Rows::parse(input)?;
+    let keys = rows.required::<TeamKey>("key")?;
+    let numbers = rows.required::<u32>("team_number")?;
+    let names = rows.column("name")?.map(|(_, name)| name.to_owned()).collect::<Vec<_>>();
+    let [nicknames, cities, states, countries] = ["nickname", "city", "state_prov", "country"]
+        .map(|name| rows.strings(name));
+    let mut fields = nicknames?.into_iter().zip(cities?).zip(states?).zip(countries?);
+    Ok(keys
+        .into_iter()
+        .zip(numbers)
+        .zip(names)
+        .map(|((key, number), name)| {
+            let (((nickname, city), state_prov), country) = fields.next().expect("Every column has a value for each line");
+            SimpleTeam { key, team_number: TeamNumber::new(number), nickname, name, city, state_prov, country }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::matches::test_util::{fixture, match_with};
+
+    use super::*;
+
+    #[test]
+    fn tables() {
+        let m = fixture();
+        let csv = match_table().to_csv([&m]);
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("key,event_key,comp_level,set_number,match_number,time,actual_time,red1,"));
+        assert!(lines.next().unwrap().ends_with(",frc1280,frc701,frc3189,frc1678,frc5430,frc8033,47,61,blue"));
+        let unplayed = match_with("2023cafr_sf2m1", &m.alliances.red.team_keys, &m.alliances.blue.team_keys[..2], None);
+        let read = read_matches(&match_table().to_csv([&m, &unplayed])).unwrap();
+        assert_eq!(read.len(), 2);
+        for (read, m) in read.iter().zip([&m, &unplayed]) {
+            assert_eq!((&read.key, &read.event_key, &read.comp_level), (&m.key, &m.event_key, &m.comp_level));
+            assert_eq!((read.set_number, read.match_number), (m.set_number, m.match_number));
+            assert_eq!((read.time, read.actual_time), (m.time, m.actual_time));
+            assert_eq!(read.winning_alliance, m.winning_alliance);
+            for (read, alliance) in [(&read.alliances.red, &m.alliances.red), (&read.alliances.blue, &m.alliances.blue)] {
+                assert_eq!((read.score, &read.team_keys), (alliance.score, &alliance.team_keys));
+            }
+        }
+        let csv = match_table().only(&["blue_score", "key"]).to_csv([&m]);
+        assert_eq!(csv, "blue_score,key\r\n61,2023cafr_qm1\r\n");
+
+        let rows = TeamMatch::rows([&m]);
+        assert_eq!(rows.len(), 6);
+        let csv = team_match_table().without(&["surrogate", "dq"]).to_csv(&rows);
+        assert_eq!(csv.lines().nth(1), Some("2023cafr_qm1,frc1280,red,1,47,61,L"));
+
+        let oprs: EventOPRs = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_oprs.json")).unwrap();
+        let csv = opr_table().to_csv(&OprRow::rows(&oprs));
+        let read = read_oprs(&csv).unwrap();
+        assert_eq!(read.oprs, oprs.oprs);
+        assert_eq!(read.ccwms, oprs.ccwms);
+
+        let rankings: EventRankings = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_rankings.json")).unwrap();
+        let table = ranking_table(&rankings);
+        assert!(table.headers().any(|header| header == "Ranking Score"));
+        assert_eq!(table.to_csv(&rankings.rankings).lines().count(), rankings.rankings.len() + 1);
+
+        let team: Team = serde_json::from_str(include_str!("../../fixtures/team_frc1280.json")).unwrap();
+        let mut other = team.simple.clone();
+        other.name = "Quote \"and\", comma\nnewline".to_owned();
+        other.city = None;
+        let csv = team_table().to_csv([&team]) + simple_team_table().to_csv([&other]).split_once("\r\n").unwrap().1;
+        let read = read_simple_teams(&csv).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].nickname, team.simple.nickname);
+        assert_eq!(read[1].name, other.name);
+        assert_eq!(read[1].city, None);
+        assert!(matches!(read_oprs("team_key,opr\r\nfrc1,x\r\n"), Err(CsvError::Value { line: 2, .. })));
+        // Blank lines and fields spanning several lines are counted in line numbers
+        let csv = "team_key,name,opr\r\n\r\nfrc1,\"two\nlines\",1\r\nfrc2,,x\r\n";
+        assert!(matches!(read_oprs(csv), Err(CsvError::Value { line: 5, .. })));
+        assert!(matches!(read_simple_teams("key\r\n"), Err(CsvError::MissingColumn(_))));
+    }
+}
@@ -0,0 +1,3 @@
+//! Conversion of API data to and from file formats used outside of Rust
+
+pub mod csv;
@@ -0,0 +1,220 @@
+//! Distances between the locations of teams and events
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    ctx::{endpoints::EndPoint, Context},
+    model::{event::Event, team::{SimpleTeam, Team}, Year},
+    Error,
+};
+
+/// Mean radius of the Earth in kilometers
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// Approximate centroids of countries, states and provinces, and cities, keyed by lowercase
+/// `(country, state_prov, city)` with empty strings for the parts a centroid does not cover. Only
+/// a few dozen cities with many teams are bundled, so most places resolve to a state or country
+static CENTROIDS: Lazy<HashMap<(String, String, String), Coordinates>> = Lazy::new(|| {
+    let mut centroids = HashMap::new();
+    let lines = include_str!("centroids.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for line in lines {
+        let fields = line.split('|').collect::<Vec<_>>();
+        let [countries, states, cities, lat, lng] = fields[..] else { panic!("Malformed centroid '{}'", line) };
+        let coords = Coordinates {
+            lat: lat.parse().expect("Centroid latitude is a number"),
+            lng: lng.parse().expect("Centroid longitude is a number"),
+        };
+        for country in countries.split(';') {
+            for state in states.split(';') {
+                for city in cities.split(';') {
+                    centroids.insert((normalize(country), normalize(state), normalize(city)), coords);
+                }
+            }
+        }
+    }
+    centroids
+});
+
+/// A point on the surface of the Earth, in degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl Coordinates {
+    /// Get the great-circle distance between two points in kilometers
+    pub fn distance_km(&self, other: &Self) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlng = (other.lng - self.lng).to_radians();
+        let a = (dlat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (dlng / 2.).sin().powi(2);
+        2. * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+
+    /// Get the approximate centroid of a city, or of its state or province or country if the
+    /// city is not in the bundled lookup table
+    pub fn centroid(city: Option<&str>, state_prov: Option<&str>, country: Option<&str>) -> Option<Self> {
+        let country = normalize(country?);
+        let state = normalize(state_prov.unwrap_or_default());
+        let city = normalize(city.unwrap_or_default());
+        CENTROIDS
+            .get(&(country.clone(), state.clone(), city))
+            .or_else(|| CENTROIDS.get(&(country.clone(), state, String::new())))
+            .or_else(|| CENTROIDS.get(&(country, String::new(), String::new())))
+            .copied()
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Trait implemented by models that have a location, used to compute the distance between them
+pub trait Located {
+    /// Get the coordinates of this item, falling back to the centroid of its city, state or
+    /// province, or country if its exact coordinates are not known.
+    ///
+    /// Few cities are in the bundled centroid table, so without exact coordinates the result is
+    /// usually only accurate to the state or province, and for countries without state
+    /// centroids only to the country
+    fn coordinates(&self) -> Option<Coordinates>;
+
+    /// Get the great-circle distance in kilometers between this item and another, if both can
+    /// be located
+    fn distance_km(&self, other: &impl Located) -> Option<f64> {
+        Some(self.coordinates()?.distance_km(&other.coordinates()?))
+    }
+}
+
+impl Located for Coordinates {
+    fn coordinates(&self) -> Option<Coordinates> {
+        Some(*self)
+    }
+}
+
+impl Located for SimpleTeam {
+    fn coordinates(&self) -> Option<Coordinates> {
+        Coordinates::centroid(self.city.as_deref(), self.state_prov.as_deref(), self.country.as_deref())
+    }
+}
+
+impl Located for Team {
+    fn coordinates(&self) -> Option<Coordinates> {
+        match (self.lat, self.lng) {
+            (Some(lat), Some(lng)) => Some(Coordinates { lat, lng }),
+            _ => self.simple.coordinates(),
+        }
+    }
+}
+
+impl Located for Event {
+    fn coordinates(&self) -> Option<Coordinates> {
+        match (self.lat, self.lng) {
+            (Some(lat), Some(lng)) => Some(Coordinates { lat, lng }),
+            _ => Coordinates::centroid(self.simple.city.as_deref(), self.simple.state_prov.as_deref(), self.simple.country.as_deref()),
+        }
+    }
+}
+
+/// Get the items within `radius_km` kilometers of `origin` with their distances, nearest first.
+/// Items that cannot be located are skipped
+pub fn within<'a, T: Located>(origin: &impl Located, items: &'a [T], radius_km: f64) -> Vec<(&'a T, f64)> {
+    let Some(origin) = origin.coordinates() else { return Vec::new() };
+    let mut near = items
+        .iter()
+        .filter_map(|item| Some((item, item.coordinates()?.distance_km(&origin))))
+        .filter(|(_, distance)| *distance <= radius_km)
+        .collect::<Vec<_>>();
+    near.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    near
+}
+
+/// Get the events of the given year within `radius_km` kilometers of a team, nearest first
+pub async fn events_near(team: &Team, year: Year, radius_km: f64, ctx: &Context) -> Result<Vec<(Event, f64)>, Error> {
+    let events = ctx.endpoints.events.by_year.get((year,), ctx).await?;
+    Ok(within(team, &events, radius_km)
+        .into_iter()
+        .map(|(event, distance)| (event.clone(), distance))
+        .collect())
+}
+
+/// Get the teams active in the year of an event that are within `radius_km` kilometers of it,
+/// nearest first.
+///
+/// The API rarely has exact coordinates for teams, so most are placed at the centroid of their
+/// state or province (see [Located::coordinates]). Distances are then only state-level, and
+/// a radius smaller than a state can include or leave out whole states at once
+pub async fn teams_near(event: &Event, radius_km: f64, ctx: &Context) -> Result<Vec<(Team, f64)>, Error> {
+    let mut teams = Vec::new();
+    for page in 0.. {
+        let page = ctx.endpoints.teams.team_by_year.get((event.simple.year, page), ctx).await?;
+        if page.is_empty() {
+            break
+        }
+        teams.extend(page.iter().cloned());
+    }
+
+    Ok(within(event, &teams, radius_km)
+        .into_iter()
+        .map(|(team, distance)| (team.clone(), distance))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nearby() {
+        let houston = Coordinates::centroid(Some("Houston"), Some("TX"), Some("USA")).unwrap();
+        let detroit = Coordinates::centroid(Some("Detroit"), Some("Michigan"), Some("United States")).unwrap();
+        assert!((houston.distance_km(&detroit) - 1784.).abs() < 20.);
+
+        let mut team: Team = serde_json::from_str(include_str!("../../fixtures/team_frc1280.json")).unwrap();
+        team.lat = None;
+        team.lng = None;
+        let event: Event = serde_json::from_str(include_str!("../../fixtures/event_2023cafr.json")).unwrap();
+        let mut far = event.clone();
+        far.lat = Some(houston.lat);
+        far.lng = Some(houston.lng);
+        let events = [far, event];
+        let near = within(&team, &events, 500.);
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].0.simple.key.to_string(), "2023cafr");
+    }
+
+    #[test]
+    fn fallback() {
+        let california = Coordinates::centroid(None, Some("California"), Some("USA")).unwrap();
+        let usa = Coordinates::centroid(None, None, Some("USA")).unwrap();
+        let canada = Coordinates::centroid(None, None, Some("Canada")).unwrap();
+
+        // Cities missing from the table fall back to their state, matched by name or code
+        assert_eq!(Coordinates::centroid(Some("Modesto"), Some("California"), Some("USA")), Some(california));
+        assert_eq!(Coordinates::centroid(Some("Modesto"), Some("ca"), Some("United States")), Some(california));
+        let san_jose = Coordinates::centroid(Some(" san jose "), Some("CA"), Some("US")).unwrap();
+        assert!(san_jose.distance_km(&california) > 100.);
+
+        // Unknown or missing states fall back to their country
+        assert_eq!(Coordinates::centroid(Some("Nowhere"), Some("Atlantis"), Some("USA")), Some(usa));
+        assert_eq!(Coordinates::centroid(Some("Nowhere"), None, Some("Canada")), Some(canada));
+
+        // Places in unknown or missing countries cannot be located
+        assert_eq!(Coordinates::centroid(Some("Houston"), Some("TX"), Some("Atlantis")), None);
+        assert_eq!(Coordinates::centroid(Some("Houston"), Some("TX"), None), None);
+
+        let mut team: Team = serde_json::from_str(include_str!("../../fixtures/team_frc1280.json")).unwrap();
+        team.lat = None;
+        team.lng = None;
+        team.simple.city = Some("Modesto".to_owned());
+        team.simple.state_prov = Some("CA".to_owned());
+        assert_eq!(team.coordinates(), Some(california));
+        team.simple.state_prov = None;
+        assert_eq!(team.coordinates(), Some(usa));
+    }
+}
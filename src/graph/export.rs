@@ -0,0 +1,69 @@
+//! Export of team graphs to formats read by graph visualization tools
+
+use std::fmt::Write;
+
+use serde_json::json;
+
+use super::TeamGraph;
+
+impl TeamGraph {
+    /// Render this graph as GraphML, with the partner, opponent and total counts of each edge
+    /// as attributes
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(out, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#).unwrap();
+        for key in ["weight", "partner", "opponent"] {
+            writeln!(out, r#"  <key id="{0}" for="edge" attr.name="{0}" attr.type="int"/>"#, key).unwrap();
+        }
+        writeln!(out, r#"  <graph id="teams" edgedefault="undirected">"#).unwrap();
+        for team in self.teams.iter() {
+            writeln!(out, r#"    <node id="{}"/>"#, team.as_ref()).unwrap();
+        }
+        for (a, b, edge) in self.edges() {
+            writeln!(out, r#"    <edge source="{}" target="{}">"#, a.as_ref(), b.as_ref()).unwrap();
+            for (key, value) in [("weight", edge.weight()), ("partner", edge.partner), ("opponent", edge.opponent)] {
+                writeln!(out, r#"      <data key="{}">{}</data>"#, key, value).unwrap();
+            }
+            writeln!(out, "    </edge>").unwrap();
+        }
+        writeln!(out, "  </graph>").unwrap();
+        writeln!(out, "</graphml>").unwrap();
+        out
+    }
+
+    /// Render this graph in the Graphviz DOT language, with the counts of each edge as
+    /// attributes
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "graph teams {{").unwrap();
+        for team in self.teams.iter() {
+            writeln!(out, "  \"{}\";", team.as_ref()).unwrap();
+        }
+        for (a, b, edge) in self.edges() {
+            writeln!(
+                out,
+                "  \"{}\" -- \"{}\" [weight={}, partner={}, opponent={}];",
+                a.as_ref(), b.as_ref(), edge.weight(), edge.partner, edge.opponent,
+            ).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    /// Render this graph as node-link JSON, in the layout used by NetworkX and D3
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "directed": false,
+            "multigraph": false,
+            "nodes": self.teams.iter().map(|team| json!({ "id": team })).collect::<Vec<_>>(),
+            "links": self.edges().map(|(a, b, edge)| json!({
+                "source": a,
+                "target": b,
+                "weight": edge.weight(),
+                "partner": edge.partner,
+                "opponent": edge.opponent,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
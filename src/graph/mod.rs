@@ -0,0 +1,234 @@
+//! Graph of the teams that have played with or against each other in a set of matches
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::model::{matches::Match, team::TeamKey};
+
+mod export;
+
+/// Number of times two teams have met in the matches of a graph
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Edge {
+    /// Matches the teams played on the same alliance
+    pub partner: u32,
+    /// Matches the teams played on opposing alliances
+    pub opponent: u32,
+}
+
+impl Edge {
+    /// Get the total number of matches the teams played together
+    pub fn weight(&self) -> u32 {
+        self.partner + self.opponent
+    }
+}
+
+/// Undirected graph with a node for each team and an edge between each pair of teams that have
+/// met in a match
+#[derive(Clone, Debug, Default)]
+pub struct TeamGraph {
+    teams: Vec<TeamKey>,
+    index: HashMap<TeamKey, usize>,
+    /// Edges keyed by the indices of their teams, lowest first
+    edges: BTreeMap<(usize, usize), Edge>,
+}
+
+impl TeamGraph {
+    /// Build the graph of every team in the given matches, including matches that have not
+    /// been played yet
+    pub fn new<'a>(matches: impl IntoIterator<Item = &'a Match>) -> Self {
+        let mut graph = Self::default();
+        for m in matches {
+            let red = m.alliances.red.team_keys.iter().map(|team| graph.node(team)).collect::<Vec<_>>();
+            let blue = m.alliances.blue.team_keys.iter().map(|team| graph.node(team)).collect::<Vec<_>>();
+            for alliance in [&red, &blue] {
+                for (i, a) in alliance.iter().enumerate() {
+                    for b in &alliance[i + 1..] {
+                        graph.edge_mut(*a, *b).partner += 1;
+                    }
+                }
+            }
+            for a in red.iter() {
+                for b in blue.iter() {
+                    graph.edge_mut(*a, *b).opponent += 1;
+                }
+            }
+        }
+        graph
+    }
+
+    /// Get every team in this graph, in the order they first appear in its matches
+    pub fn teams(&self) -> &[TeamKey] {
+        &self.teams
+    }
+
+    /// Get the edge between two teams, or `None` if they have not met
+    pub fn edge(&self, a: &TeamKey, b: &TeamKey) -> Option<Edge> {
+        let (a, b) = (*self.index.get(a)?, *self.index.get(b)?);
+        self.edges.get(&(a.min(b), a.max(b))).copied()
+    }
+
+    /// Iterate over every edge of this graph with the teams it connects
+    pub fn edges(&self) -> impl Iterator<Item = (&TeamKey, &TeamKey, Edge)> {
+        self.edges.iter().map(|((a, b), edge)| (&self.teams[*a], &self.teams[*b], *edge))
+    }
+
+    /// Iterate over the teams that a team has met, with the edge to each of them
+    pub fn neighbors<'a>(&'a self, team: &TeamKey) -> impl Iterator<Item = (&'a TeamKey, Edge)> + 'a {
+        let node = self.index.get(team).copied();
+        self.edges.iter().filter_map(move |((a, b), edge)| match node? {
+            n if n == *a => Some((&self.teams[*b], *edge)),
+            n if n == *b => Some((&self.teams[*a], *edge)),
+            _ => None,
+        })
+    }
+
+    /// Get the fraction of the other teams in the graph that each team has met
+    pub fn degree_centrality(&self) -> HashMap<TeamKey, f64> {
+        let others = self.teams.len().saturating_sub(1).max(1) as f64;
+        self.adjacency()
+            .into_iter()
+            .enumerate()
+            .map(|(i, neighbors)| (self.teams[i].clone(), neighbors.len() as f64 / others))
+            .collect()
+    }
+
+    /// Get the betweenness centrality of each team, which is the fraction of shortest paths
+    /// between pairs of other teams that pass through it, ignoring edge weights
+    pub fn betweenness_centrality(&self) -> HashMap<TeamKey, f64> {
+        let adjacency = self.adjacency();
+        let n = self.teams.len();
+        let mut centrality = vec![0.; n];
+
+        // Brandes' algorithm, with a breadth first search from each team
+        for source in 0..n {
+            let mut order = Vec::with_capacity(n);
+            let mut predecessors = vec![Vec::new(); n];
+            let mut paths = vec![0f64; n];
+            let mut distance = vec![usize::MAX; n];
+            paths[source] = 1.;
+            distance[source] = 0;
+            let mut queue = VecDeque::from([source]);
+            while let Some(v) = queue.pop_front() {
+                order.push(v);
+                for &w in adjacency[v].iter() {
+                    if distance[w] == usize::MAX {
+                        distance[w] = distance[v] + 1;
+                        queue.push_back(w);
+                    }
+                    if distance[w] == distance[v] + 1 {
+                        paths[w] += paths[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+
+            let mut dependency = vec![0.; n];
+            for &w in order.iter().rev() {
+                for &v in predecessors[w].iter() {
+                    dependency[v] += paths[v] / paths[w] * (1. + dependency[w]);
+                }
+                if w != source {
+                    centrality[w] += dependency[w];
+                }
+            }
+        }
+
+        // Each path was counted from both of its ends
+        let pairs = match n {
+            0..=2 => 1.,
+            n => ((n - 1) * (n - 2)) as f64,
+        };
+        centrality
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| (self.teams[i].clone(), c / pairs))
+            .collect()
+    }
+
+    /// Get the groups of teams that are connected by a chain of matches, largest first
+    pub fn components(&self) -> Vec<Vec<TeamKey>> {
+        let adjacency = self.adjacency();
+        let mut seen = vec![false; self.teams.len()];
+        let mut components = Vec::new();
+        for start in 0..self.teams.len() {
+            if seen[start] {
+                continue
+            }
+            seen[start] = true;
+            let mut component = Vec::new();
+            let mut stack = vec![start];
+            while let Some(v) = stack.pop() {
+                component.push(self.teams[v].clone());
+                for &w in adjacency[v].iter().filter(|w| !seen[**w]).collect::<Vec<_>>() {
+                    seen[w] = true;
+                    stack.push(w);
+                }
+            }
+            components.push(component);
+        }
+        components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+        components
+    }
+
+    fn node(&mut self, team: &TeamKey) -> usize {
+        if let Some(i) = self.index.get(team) {
+            return *i
+        }
+        self.teams.push(team.clone());
+        self.index.insert(team.clone(), self.teams.len() - 1);
+        self.teams.len() - 1
+    }
+
+    fn edge_mut(&mut self, a: usize, b: usize) -> &mut Edge {
+        self.edges.entry((a.min(b), a.max(b))).or_default()
+    }
+
+    /// Get the neighbors of each node by index
+    fn adjacency(&self) -> Vec<Vec<usize>> {
+        let mut adjacency = vec![Vec::new(); self.teams.len()];
+        for (a, b) in self.edges.keys() {
+            adjacency[*a].push(*b);
+            adjacency[*b].push(*a);
+        }
+        adjacency
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::matches::test_util::{fixture, match_with};
+
+    use super::*;
+
+    #[test]
+    fn team_graph() {
+        let m = fixture();
+        let (red, blue) = (m.alliances.red.team_keys.clone(), m.alliances.blue.team_keys.clone());
+        let outsiders = ["frc9998", "frc9999"].map(|team| team.parse::<TeamKey>().unwrap());
+        let other = match_with("2023cafr_qm2", &outsiders[..1], &outsiders[1..], Some((47, 61)));
+
+        let graph = TeamGraph::new([&m, &m, &other]);
+        assert_eq!(graph.teams().len(), 8);
+        assert_eq!(graph.edge(&red[0], &red[1]), Some(Edge { partner: 2, opponent: 0 }));
+        assert_eq!(graph.edge(&red[0], &blue[0]).map(|edge| edge.weight()), Some(2));
+        assert_eq!(graph.edge(&red[0], &outsiders[0]), None);
+        assert_eq!(graph.neighbors(&red[0]).count(), 5);
+        assert_eq!(graph.edges().count(), 16);
+
+        let components = graph.components();
+        assert_eq!(components.iter().map(Vec::len).collect::<Vec<_>>(), [6, 2]);
+        assert_eq!(graph.degree_centrality()[&red[0]], 5. / 7.);
+        assert!(graph.betweenness_centrality().values().all(|c| *c == 0.));
+        let chain = [(3, &red[..1], &red[1..2]), (4, &red[1..2], &red[2..])]
+            .map(|(n, a, b)| match_with(&format!("2023cafr_qm{}", n), a, b, Some((47, 61))));
+        assert_eq!(TeamGraph::new(&chain).betweenness_centrality()[&red[1]], 1.);
+
+        let graphml = graph.to_graphml();
+        assert!(graphml.contains(r#"<node id="frc9999"/>"#));
+        assert_eq!(graphml.matches("<edge ").count(), 16);
+        assert!(graph.to_dot().contains(r#""frc9998" -- "frc9999" [weight=1, partner=0, opponent=1];"#));
+        let json = graph.to_json();
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 8);
+        assert_eq!(json["links"][0]["source"], red[0].as_ref());
+    }
+}
@@ -1,6 +1,14 @@
 pub mod model;
 pub mod ctx;
 pub mod error;
+pub mod webhook;
+pub mod trusted;
+pub mod analysis;
+pub mod geo;
+pub mod bracket;
+pub mod graph;
+pub mod export;
+pub mod store;
 
 pub use error::Error;
 
@@ -8,9 +16,7 @@ pub use error::Error;
 mod test {
     use std::sync::Arc;
 
-    use crate::{model::{Year, team::Team, id::Key}, ctx::{Context, endpoints::EndPoint}};
-
-    use super::*;
+    use crate::{model::{Year, id::Key}, ctx::{Context, endpoints::EndPoint}};
 
     #[tokio::test]
     async fn event_test() {
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Year, event::EventKey, team::TeamKey};
+
+/// An award given to one or more recipients at an [Event](super::event::Event)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Award {
+    pub name: String,
+    /// Numeric award type as defined by TBA's `AwardType` enumeration
+    pub award_type: u32,
+    pub event_key: EventKey,
+    pub recipient_list: Vec<AwardRecipient>,
+    pub year: Year,
+}
+
+/// A team and/or individual that received an [Award]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AwardRecipient {
+    pub team_key: Option<TeamKey>,
+    pub awardee: Option<String>,
+}
@@ -1,12 +1,11 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
 use crate::{ctx::endpoints::EndPoint, key};
 
-use super::{Year, team::TeamKey};
-use chrono::NaiveDate;
-use serde::Deserialize;
-use serde_repr::Deserialize_repr;
-use url::Url;
+use super::{Year, id::{parse_digits, ParseKeyError}, matches::MatchKey, team::TeamKey};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
 
 key!(EventKey(String) -> Event => (self, ctx) with ctx
             .endpoints
@@ -16,24 +15,57 @@ key!(EventKey(String) -> Event => (self, ctx) with ctx
             .await
 );
 
-#[derive(Clone, Copy, Deserialize_repr, Debug)]
-#[repr(i8)]
-pub enum EventType {
-    Regional = 0,
-    District = 1,
-    DistrictCmp = 2,
-    CmpDivision = 3,
-    CmpFinals = 4,
-    DistrictCmpDivision = 5,
-    FOC = 6,
-    Remote = 7,
-
-    Offseason = 99,
-    Preseason = 100,
-    Unlabeled = -1,
+impl EventKey {
+    /// Create a new event key from the year the event took place and its event code
+    pub fn new(year: Year, code: &str) -> Result<Self, ParseKeyError> {
+        format!("{}{}", year, code).parse()
+    }
+
+    /// Get the year that the event this key refers to took place in
+    pub fn year(&self) -> Year {
+        Year(parse_digits(&self.0[..4]).expect("Event key was validated when created") as u16)
+    }
+
+    /// Get the event code following the year in this key, e.g. `casj` in `2023casj`
+    pub fn code(&self) -> &str {
+        &self.0[4..]
+    }
+}
+
+impl FromStr for EventKey {
+    type Err = ParseKeyError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s
+            .get(..4)
+            .and_then(parse_digits)
+            .and_then(|year| Year::new(year as u16).ok())
+            .ok_or_else(|| ParseKeyError::EventYear(s.to_owned()))?;
+        let code = &s[4..];
+        match !code.is_empty() && code.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit()) {
+            true => Ok(Self(s.to_owned())),
+            false => Err(ParseKeyError::EventCode(s.to_owned())),
+        }
+    }
+}
+
+int_enum! {
+    pub enum EventType ("event type") {
+        Regional = 0,
+        District = 1,
+        DistrictCmp = 2,
+        CmpDivision = 3,
+        CmpFinals = 4,
+        DistrictCmpDivision = 5,
+        FOC = 6,
+        Remote = 7,
+
+        Offseason = 99,
+        Preseason = 100,
+        Unlabeled = -1,
+    }
 }
 
-#[derive(Clone, Debug, Deserialize,)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SimpleEvent {
     pub key: EventKey,
     pub name: String,
@@ -43,14 +75,14 @@ pub struct SimpleEvent {
     pub city: Option<String>,
     pub state_prov: Option<String>,
     pub country: Option<String>,
-    #[serde(deserialize_with="super::deserialize_yyyymmdd")]
+    #[serde(deserialize_with="super::deserialize_yyyymmdd", serialize_with="super::serialize_yyyymmdd")]
     pub start_date: NaiveDate,
-    #[serde(deserialize_with="super::deserialize_yyyymmdd")]
+    #[serde(deserialize_with="super::deserialize_yyyymmdd", serialize_with="super::serialize_yyyymmdd")]
     pub end_date: NaiveDate,
     pub year: Year,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DistrictList {
     pub abbreviation: String,
     pub display_name: String,
@@ -58,7 +90,7 @@ pub struct DistrictList {
     pub year: Year,
 }
 
-#[derive(Clone, Debug, Deserialize,)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Event {
     #[serde(flatten)]
     pub simple: SimpleEvent,
@@ -68,67 +100,76 @@ pub struct Event {
     pub address: Option<String>,
     pub postal_code: Option<String>,
     pub gmaps_place_id: Option<String>,
-    pub gmaps_url: Option<Url>,
+    pub gmaps_url: Option<String>,
     pub lat: Option<f64>,
     pub lng: Option<f64>,
     pub location_name: Option<String>,
     pub timezone: Option<String>,
-    pub website: Option<Url>,
+    pub website: Option<String>,
     pub first_event_id: Option<String>,
     pub first_event_code: Option<String>,
     pub webcasts: Vec<WebCast>,
     pub division_keys: Vec<EventKey>,
-    pub parent_event_key: String,
+    pub parent_event_key: Option<EventKey>,
     pub playoff_type: Option<PlayoffType>,
     pub playoff_type_string: Option<String>,
-
 }
 
-#[derive(Clone, Debug)]
-pub enum WebCastType {
-    Youtube,
-    Twitch,
-    Ustream,
-    Iframe,
-    Html5,
-    Rtmp,
-    Livestream,
-    DirectLink,
-    Mms,
-    Justin,
-    Stemtv,
-    Dacast,
+string_enum! {
+    pub enum WebCastType ("webcast type") {
+        Youtube = "youtube",
+        Twitch = "twitch",
+        Ustream = "ustream",
+        Iframe = "iframe",
+        Html5 = "html5",
+        Rtmp = "rtmp",
+        Livestream = "livestream",
+        DirectLink = "direct_link",
+        Mms = "mms",
+        Justin = "justin",
+        Stemtv = "stemtv",
+        Dacast = "dacast",
+    }
 }
 
-#[derive(Clone, Debug, Deserialize_repr)]
-#[repr(u8)]
-pub enum PlayoffType {
-    Bracket8Team = 0,
-    Bracket16Team = 1,
-    Bracket4Team = 2,
-    AvgScore8Team = 3,
-    RoundRobin6Team = 4,
-    DoubleElim8Team = 5,
-    Bo5Finals = 6,
-    Bo3Finals = 7,
-    Custom = 8,
+int_enum! {
+    pub enum PlayoffType ("playoff type") {
+        Bracket8Team = 0,
+        Bracket16Team = 1,
+        Bracket4Team = 2,
+        AvgScore8Team = 3,
+        RoundRobin6Team = 4,
+        /// Double elimination bracket used at some offseason events before 2023
+        DoubleElim8Team = 5,
+        Bo5Finals = 6,
+        Bo3Finals = 7,
+        Custom = 8,
+        Bracket2Team = 9,
+        /// Double elimination bracket used at official events since 2023. Distinct from
+        /// [DoubleElim8Team](Self::DoubleElim8Team), which keeps the code it had before this
+        /// format was added
+        ModernDoubleElim8Team = 10,
+        DoubleElim4Team = 11,
+    }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct WebCast {
     #[serde(rename="type")]
     pub type_: WebCastType,
     pub channel: String,
-    #[serde(deserialize_with="super::deserialize_yyyymmdd_opt")]
+    #[serde(
+        default,
+        deserialize_with="super::deserialize_yyyymmdd_opt",
+        serialize_with="super::serialize_yyyymmdd_opt",
+        skip_serializing_if="Option::is_none",
+    )]
     pub date: Option<NaiveDate>,
+    #[serde(skip_serializing_if="Option::is_none")]
     pub file: Option<String>,
-    pub division_keys: Option<Vec<EventKey>>,
-    pub parent_event_key: Option<EventKey>,
-    pub playoff_type: Option<PlayoffType>,
-    pub playoff_type_string: Option<String>,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct TeamEventStatusAlliance {
     pub name: Option<String>,
     pub number: u16,
@@ -136,38 +177,48 @@ pub struct TeamEventStatusAlliance {
     pub pick: u8,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct TeamEventStatusAllianceBackup {
     pub out: Option<TeamKey>,
     #[serde(rename="in")]
     pub in_: Option<TeamKey>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum PlayoffLevel {
-    QM,
-    EF,
-    QF,
-    SF,
-    F,
+string_enum! {
+    /// Competition level of a match, ordered by when each level is played at an event
+    #[derive(PartialOrd, Ord)]
+    pub enum PlayoffLevel ("playoff level") {
+        QM = "qm",
+        EF = "ef",
+        QF = "qf",
+        SF = "sf",
+        F = "f",
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum PlayoffStatus {
-    Won,
-    Eliminated,
-    Playing,
+string_enum! {
+    pub enum PlayoffStatus ("playoff status") {
+        Won = "won",
+        Eliminated = "eliminated",
+        Playing = "playing",
+    }
 }
 
-#[derive(Clone,Debug,Deserialize)]
+/// Playoff status of a team or alliance at an event
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct TeamEventStatusPlayoff {
     pub level: PlayoffLevel,
-    pub current_level_record: WLTRecord,
-    pub record: WLTRecord,
-    pub status: String,
+    pub current_level_record: Option<WLTRecord>,
+    pub record: Option<WLTRecord>,
+    pub status: PlayoffStatus,
+    pub playoff_average: Option<f64>,
+    /// Round of the double elimination bracket the alliance last played in, for events with
+    /// double elimination playoffs
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub double_elim_round: Option<String>,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct TeamEventStatusRank {
     pub num_teams: Option<u16>,
     pub ranking: Option<TeamEventStatusRankRanking>,
@@ -175,16 +226,18 @@ pub struct TeamEventStatusRank {
     pub status: Option<String>,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct TeamEventStatusRankSortOrderInfo {
     pub precision: Option<u16>,
     pub name: Option<String>,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct TeamEventStatusRankRanking {
     pub matches_played: Option<u16>,
     pub qual_average: Option<f64>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub extra_stats: Option<Vec<f64>>,
     pub sort_orders: Option<Vec<f64>>,
     pub record: Option<WLTRecord>,
     pub rank: Option<u32>,
@@ -193,49 +246,66 @@ pub struct TeamEventStatusRankRanking {
 }
 
 /// A Win-Loss-Tie record for a team or alliance
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct WLTRecord {
     pub losses: Option<u16>,
     pub wins: Option<u16>,
     pub ties: Option<u16>,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+/// Status of a team at an event, with each section missing until the team reaches that part of
+/// the event
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct TeamEventStatus {
-    pub qual: TeamEventStatusRank,
-    pub alliance: TeamEventStatusAlliance,
+    pub qual: Option<TeamEventStatusRank>,
+    /// Alliance the team was on in the playoffs, or `None` if the team was not picked
+    pub alliance: Option<TeamEventStatusAlliance>,
+    pub playoff: Option<TeamEventStatusPlayoff>,
+    /// HTML summary of the team's alliance, or `--` if the team was not picked
+    pub alliance_status_str: Option<String>,
+    /// HTML summary of the team's playoff results, or `--` if the team did not play in playoffs
+    pub playoff_status_str: Option<String>,
+    /// HTML summary of the team's results at the whole event
+    pub overall_status_str: Option<String>,
+    pub next_match_key: Option<MatchKey>,
+    pub last_match_key: Option<MatchKey>,
+}
+
+/// Rankings of all teams at an event, with the names of the values in each team's sort orders
+#[derive(Clone,Debug,Deserialize,Serialize)]
+pub struct EventRankings {
+    pub rankings: Vec<TeamEventStatusRankRanking>,
+    pub extra_stats_info: Option<Vec<TeamEventStatusRankSortOrderInfo>>,
+    pub sort_order_info: Vec<TeamEventStatusRankSortOrderInfo>,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct EliminationAlliance {
     pub name: Option<String>,
     pub backup: Option<TeamEventStatusAllianceBackup>,
     pub declined: Vec<TeamKey>,
     pub picks: Vec<TeamKey>,
-    pub status: EliminationAllianceStatus,
+    /// Playoff status of this alliance, or `None` if playoffs have not started
+    pub status: Option<EliminationAllianceStatus>,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct EventOPRs {
     pub oprs: HashMap<TeamKey, f64>,
     pub dprs: HashMap<TeamKey, f64>,
     pub ccwms: HashMap<TeamKey, f64>,
 }
 
-#[derive(Clone,Debug,Deserialize)]
-pub struct EliminationAllianceStatus {
-    pub playoff_average: f64,
-    #[serde(flatten)]
-    pub team_event: TeamEventStatusPlayoff,
-}
+/// Playoff status of an alliance, which has the same fields as that of a single team
+pub type EliminationAllianceStatus = TeamEventStatusPlayoff;
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,PartialEq,Eq,Deserialize,Serialize)]
 pub struct EventDistrictPoints {
-    pub points: HashMap<TeamKey, EventDistrictPoints>,
+    pub points: HashMap<TeamKey, EventDistrictPointsPoints>,
     pub tiebreakers: HashMap<TeamKey, EventDistrictPointsTiebreaker>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct EventDistrictPointsPoints {
     pub total: i32,
     pub alliance_points: i32,
@@ -244,71 +314,60 @@ pub struct EventDistrictPointsPoints {
     pub qual_points: i32,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,PartialEq,Eq,Deserialize,Serialize)]
 pub struct EventDistrictPointsTiebreaker {
     pub highest_qual_scores: Vec<i32>,
     pub qual_wins: u32,
 }
 
-impl AsRef<SimpleEvent> for Event {
-    fn as_ref(&self) -> &SimpleEvent {
-        &self.simple
+impl Event {
+    /// Get the timezone of this event's venue, if it is known and is a valid IANA timezone name
+    pub fn tz(&self) -> Option<Tz> {
+        self.timezone.as_deref()?.parse().ok()
     }
-}
-impl AsMut<SimpleEvent> for Event {
-    fn as_mut(&mut self) -> &mut SimpleEvent {
-        &mut self.simple
+
+    /// Convert a UTC time into the local time of this event's venue
+    pub fn local_time(&self, time: DateTime<Utc>) -> Option<DateTime<Tz>> {
+        Some(time.with_timezone(&self.tz()?))
     }
 }
 
+impl TeamEventStatus {
+    /// Get the alliance summary of this status as plain text, or `None` if the team was not
+    /// picked for an alliance
+    pub fn alliance_status_text(&self) -> Option<String> {
+        status_text(self.alliance_status_str.as_deref())
+    }
 
-impl<'de> Deserialize<'de> for WebCastType {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: serde::Deserializer<'de> {
-        let str = <&str as Deserialize>::deserialize(deserializer)?;
-        Ok(match str {
-            "youtube" => Self::Youtube,
-            "twitch" => Self::Twitch,
-            "ustream" => Self::Ustream,
-            "iframe" => Self::Iframe,
-            "html5" => Self::Html5,
-            "rtmp" => Self::Rtmp,
-            "livestream" => Self::Livestream,
-            "direct_link" => Self::DirectLink,
-            "mms" => Self::Mms,
-            "justin" => Self::Justin,
-            "stemtv" => Self::Stemtv,
-            "dacast" => Self::Dacast,
-            _ => return Err(serde::de::Error::custom(format!("Unknown webcast type '{}'", str)))
-        })
+    /// Get the playoff summary of this status as plain text, or `None` if the team has not
+    /// played in the playoffs
+    pub fn playoff_status_text(&self) -> Option<String> {
+        status_text(self.playoff_status_str.as_deref())
+    }
+
+    /// Get the summary of the team's results at the whole event as plain text
+    pub fn overall_status_text(&self) -> Option<String> {
+        status_text(self.overall_status_str.as_deref())
     }
 }
-impl<'de> Deserialize<'de> for PlayoffLevel {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: serde::Deserializer<'de> {
-        let str = <&str as Deserialize>::deserialize(deserializer)?;
-        Ok(match str {
-            "qm" => Self::QM,
-            "ef" => Self::EF,
-            "qf" => Self::QF,
-            "sf" => Self::SF,
-            "f" => Self::F,
-            _ => return Err(serde::de::Error::custom(format!("Unknown playoff level '{}'", str)))
-        })
+
+/// Strip HTML from a status string, treating the `--` placeholder TBA uses for missing sections
+/// as no status
+fn status_text(html: Option<&str>) -> Option<String> {
+    let text = super::strip_html(html?);
+    match text.trim() {
+        "" | "--" => None,
+        _ => Some(text),
     }
 }
-impl<'de> Deserialize<'de> for PlayoffStatus {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: serde::Deserializer<'de> {
-        let str = <&str as Deserialize>::deserialize(deserializer)?;
-        Ok(match str {
-            "won" => Self::Won,
-            "eliminated" => Self::Eliminated,
-            "playing" => Self::Playing,
-            _ => return Err(serde::de::Error::custom(format!("Unknown ")))
-        })
+
+impl AsRef<SimpleEvent> for Event {
+    fn as_ref(&self) -> &SimpleEvent {
+        &self.simple
+    }
+}
+impl AsMut<SimpleEvent> for Event {
+    fn as_mut(&mut self) -> &mut SimpleEvent {
+        &mut self.simple
     }
 }
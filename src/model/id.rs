@@ -1,13 +1,34 @@
 use std::sync::Arc;
 use async_trait::async_trait;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use crate::{ctx::Context, Error};
 
+use super::team::TeamKey;
+
 /// A team number that uniquely identifies an FRC [Team](super::team::Team)
-#[derive(Clone, Copy, Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 #[serde(transparent)]
 pub struct TeamNumber(u32);
 
+/// Error returned when parsing a [TeamKey], [EventKey], or [MatchKey] from a malformed string
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseKeyError {
+    #[error("Team key '{0}' does not start with 'frc'")]
+    TeamPrefix(String),
+    #[error("Team key '{0}' does not contain a valid team number")]
+    TeamNumber(String),
+    #[error("Event key '{0}' does not start with a 4 digit year")]
+    EventYear(String),
+    #[error("Event key '{0}' does not contain a valid event code")]
+    EventCode(String),
+    #[error("Match key '{0}' does not contain an '_' separating the event key from the match")]
+    MatchSeparator(String),
+    #[error("Match key '{0}' does not contain a valid competition level")]
+    CompLevel(String),
+    #[error("Match key '{0}' does not contain a valid set and match number")]
+    MatchNumber(String),
+}
+
 /// Trait implemented by all key references in the TBA API, with method to upgrade the reference
 /// into a concrete value
 #[async_trait(?Send)]
@@ -17,22 +38,57 @@ pub trait Key: Sized {
     async fn upgrade(self, ctx: &Context) -> Result<Arc<Self::Referenced>, Error>;
 }
 
+/// Parse a string made up only of ASCII digits into a number
+pub(crate) fn parse_digits(s: &str) -> Option<u32> {
+    match s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        true => None,
+        false => s.parse().ok(),
+    }
+}
+
 #[macro_export]
 macro_rules! key {
     ($name:ident($internal:ty) -> $referenced:ty => ($this:ident, $ctxi:ident) with $ep:expr) => {
-        #[derive(Clone, Debug, PartialEq, Eq, Hash, ::serde::Deserialize)]
-        #[serde(transparent)]
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, ::serde::Deserialize, ::serde::Serialize)]
+        #[serde(try_from = "String", into = "String")]
         #[repr(transparent)]
         pub struct $name($internal);
+
+        impl ::std::convert::TryFrom<$internal> for $name {
+            type Error = $crate::model::id::ParseKeyError;
+            fn try_from(s: $internal) -> Result<Self, Self::Error> {
+                s.parse()
+            }
+        }
+
+        impl ::std::convert::From<$name> for $internal {
+            fn from(key: $name) -> Self {
+                key.0
+            }
+        }
+
+        impl ::std::convert::AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
         
         #[::async_trait::async_trait(?Send)]
-        impl crate::model::id::Key for $name {
+        impl $crate::model::id::Key for $name {
             type Referenced = $referenced;
-            async fn upgrade($this: Self, $ctxi: &crate::ctx::Context) -> Result<::std::sync::Arc<Self::Referenced>, crate::Error> {
+            async fn upgrade($this: Self, $ctxi: &$crate::ctx::Context) -> Result<::std::sync::Arc<Self::Referenced>, $crate::Error> {
                 $ep
             }
         }
 
+        #[::async_trait::async_trait(?Send)]
+        impl<'a> $crate::model::id::Key for &'a $name {
+            type Referenced = $referenced;
+            async fn upgrade(self, ctx: &$crate::ctx::Context) -> Result<::std::sync::Arc<Self::Referenced>, $crate::Error> {
+                self.clone().upgrade(ctx).await
+            }
+        }
+
         impl ::std::fmt::Display for $name {
             fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
                 self.0.fmt(f)
@@ -51,6 +107,11 @@ impl TeamNumber {
     pub const fn val(&self) -> u32 {
         self.0
     }
+
+    /// Get the key used to refer to the team with this number
+    pub fn key(&self) -> TeamKey {
+        TeamKey::from(*self)
+    }
 }
 
 impl AsRef<u32> for TeamNumber {
@@ -64,3 +125,37 @@ impl std::fmt::Display for TeamNumber {
         self.0.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{event::{EventKey, PlayoffLevel}, matches::MatchKey};
+
+    #[test]
+    fn parse_keys() {
+        let team: TeamKey = "frc1280".parse().unwrap();
+        assert_eq!(team.number(), TeamNumber::new(1280));
+        assert_eq!(TeamNumber::new(1280).key(), team);
+        assert_eq!("frc1678B".parse::<TeamKey>().unwrap().number(), TeamNumber::new(1678));
+        assert!(matches!("1280".parse::<TeamKey>(), Err(ParseKeyError::TeamPrefix(_))));
+        assert!(matches!("frc12a80".parse::<TeamKey>(), Err(ParseKeyError::TeamNumber(_))));
+
+        let event: EventKey = "2023casj".parse().unwrap();
+        assert_eq!(event.year().to_string(), "2023");
+        assert_eq!(event.code(), "casj");
+        assert!(matches!("23casj".parse::<EventKey>(), Err(ParseKeyError::EventYear(_))));
+        assert!(matches!("2023".parse::<EventKey>(), Err(ParseKeyError::EventCode(_))));
+
+        let qual: MatchKey = "2023casj_qm12".parse().unwrap();
+        assert_eq!(qual.event(), event);
+        assert_eq!(qual.comp_level(), PlayoffLevel::QM);
+        assert_eq!((qual.set_number(), qual.match_number()), (1, 12));
+        let playoff: MatchKey = "2023casj_sf11m1".parse().unwrap();
+        assert_eq!(playoff.comp_level(), PlayoffLevel::SF);
+        assert_eq!((playoff.set_number(), playoff.match_number()), (11, 1));
+        assert!(matches!("2023casj-qm1".parse::<MatchKey>(), Err(ParseKeyError::MatchSeparator(_))));
+        assert!(matches!("2023casj_12".parse::<MatchKey>(), Err(ParseKeyError::CompLevel(_))));
+        assert_eq!("2023casj_xx1m2".parse::<MatchKey>().unwrap().comp_level(), PlayoffLevel::Unknown("xx".to_owned()));
+        assert!(matches!("2023casj_sf1".parse::<MatchKey>(), Err(ParseKeyError::MatchNumber(_))));
+    }
+}
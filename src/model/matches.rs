@@ -1,9 +1,12 @@
-use chrono::NaiveDateTime;
-use serde::Deserialize;
+use std::{cmp::Ordering, collections::BTreeMap, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
 
 use crate::{ctx::endpoints::EndPoint, key};
 
-use super::{event::{PlayoffLevel, EventKey}, team::TeamKey};
+use super::{event::{Event, PlayoffLevel, EventKey}, id::{parse_digits, ParseKeyError}, is_strict, team::TeamKey};
 
 
 key!(MatchKey(String) -> Match => (self, ctx) with ctx
@@ -14,14 +17,162 @@ key!(MatchKey(String) -> Match => (self, ctx) with ctx
             .await
 );
 
-#[derive(Clone,Copy,PartialEq,Eq,Debug)]
-pub enum MatchWinner {
+impl MatchKey {
+    /// Create the key of a match from the event it was played at, its competition level, and
+    /// its set and match numbers. The set number is ignored for qualification matches. Fails if
+    /// the competition level is not made of lowercase letters, as the key could not be split
+    /// back into its parts
+    pub fn new(event: &EventKey, comp_level: PlayoffLevel, set_number: u32, match_number: u32) -> Result<Self, ParseKeyError> {
+        let key = match &comp_level {
+            PlayoffLevel::QM => format!("{}_qm{}", event, match_number),
+            level => format!("{}_{}{}m{}", event, level.as_str(), set_number, match_number),
+        };
+        match split_match_key(&key) {
+            Ok((_, level, _, _)) if level == comp_level => Ok(Self(key)),
+            _ => Err(ParseKeyError::CompLevel(key)),
+        }
+    }
+
+    /// Get the key of the event that this match was played at
+    pub fn event(&self) -> EventKey {
+        self.components().0.parse().expect("Match key was validated when created")
+    }
+
+    /// Get the competition level of this match
+    pub fn comp_level(&self) -> PlayoffLevel {
+        self.components().1
+    }
+
+    /// Get the set number of this match, which is always 1 for qualification matches
+    pub fn set_number(&self) -> u32 {
+        self.components().2
+    }
+
+    /// Get the number of this match within its set
+    pub fn match_number(&self) -> u32 {
+        self.components().3
+    }
+
+    /// Compare two match keys by event key, then competition level, set number, and match
+    /// number, which groups the matches of each playoff series together. Unlike the [Ord]
+    /// implementation, this is not the order the matches are played in
+    pub fn cmp_by_set(&self, other: &Self) -> Ordering {
+        let (event, level, set, number) = self.components();
+        let (other_event, other_level, other_set, other_number) = other.components();
+        event.cmp(other_event)
+            .then(level.cmp(&other_level))
+            .then(set.cmp(&other_set))
+            .then(number.cmp(&other_number))
+            .then_with(|| self.0.cmp(&other.0))
+    }
+
+    fn components(&self) -> (&str, PlayoffLevel, u32, u32) {
+        split_match_key(&self.0).expect("Match key was validated when created")
+    }
+}
+
+/// Split a match key into its event key, competition level, set number, and match number,
+/// without checking that the event key is valid
+fn split_match_key(s: &str) -> Result<(&str, PlayoffLevel, u32, u32), ParseKeyError> {
+    let (event, rest) = s
+        .split_once('_')
+        .ok_or_else(|| ParseKeyError::MatchSeparator(s.to_owned()))?;
+    let level_len = rest
+        .find(|c: char| !c.is_ascii_lowercase())
+        .unwrap_or(rest.len());
+    let (level, numbers) = rest.split_at(level_len);
+    let level = match level.parse::<PlayoffLevel>() {
+        Ok(level) if !level.as_str().is_empty() => level,
+        _ => return Err(ParseKeyError::CompLevel(s.to_owned())),
+    };
+    let numbers = match level {
+        PlayoffLevel::QM => parse_digits(numbers).map(|match_number| (1, match_number)),
+        _ => numbers
+            .split_once('m')
+            .and_then(|(set, match_number)| Some((parse_digits(set)?, parse_digits(match_number)?))),
+    };
+    let (set_number, match_number) = numbers.ok_or_else(|| ParseKeyError::MatchNumber(s.to_owned()))?;
+    Ok((event, level, set_number, match_number))
+}
+
+/// Match keys are ordered by the order the matches are played in, the same as
+/// [Match::cmp_play_order]: by event key, then competition level, match number, and set number,
+/// so that e.g. `qm2` sorts before `qm10` and `qf2m1` before `qf1m2`
+impl Ord for MatchKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (event, level, set, number) = self.components();
+        let (other_event, other_level, other_set, other_number) = other.components();
+        event.cmp(other_event)
+            .then(level.cmp(&other_level))
+            .then(number.cmp(&other_number))
+            .then(set.cmp(&other_set))
+            .then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for MatchKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for MatchKey {
+    type Err = ParseKeyError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((event, _)) = s.split_once('_') {
+            event.parse::<EventKey>()?;
+        }
+        match split_match_key(s)? {
+            (_, PlayoffLevel::Unknown(_), _, _) if is_strict() => Err(ParseKeyError::CompLevel(s.to_owned())),
+            _ => Ok(Self(s.to_owned())),
+        }
+    }
+}
+
+string_enum! {
+    pub enum MatchWinner ("match winner") {
+        Red = "red",
+        Blue = "blue",
+        /// The match was tied, or has not been played yet
+        Tie = "",
+    }
+}
+
+/// Color of one of the two alliances in a match
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AllianceColor {
     Red,
     Blue,
-    Tie
 }
 
-#[derive(Debug,Clone,Deserialize)]
+impl AllianceColor {
+    /// Get the string used for this color in the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Red => "red",
+            Self::Blue => "blue",
+        }
+    }
+
+    /// Get the color of the opposing alliance
+    pub fn opponent(self) -> Self {
+        match self {
+            Self::Red => Self::Blue,
+            Self::Blue => Self::Red,
+        }
+    }
+}
+
+impl From<AllianceColor> for MatchWinner {
+    fn from(color: AllianceColor) -> Self {
+        match color {
+            AllianceColor::Red => Self::Red,
+            AllianceColor::Blue => Self::Blue,
+        }
+    }
+}
+
+#[derive(Debug,Clone,Deserialize,Serialize)]
 pub struct Match {
     pub key: MatchKey,
     pub comp_level: PlayoffLevel,
@@ -30,37 +181,61 @@ pub struct Match {
     pub alliances: MatchAlliances,
     pub winning_alliance: MatchWinner,
     pub event_key: EventKey,
-    #[serde(deserialize_with="super::deserialize_ts")]
-    pub time: NaiveDateTime,
-    #[serde(deserialize_with="super::deserialize_ts")]
-    pub actual_time: NaiveDateTime,
-    #[serde(deserialize_with="super::deserialize_ts")]
-    pub predicted_time: NaiveDateTime,
-    #[serde(deserialize_with="super::deserialize_ts")]
-    pub post_result_time: NaiveDateTime,
+    /// Scheduled start time of this match
+    #[serde(deserialize_with="super::deserialize_ts_opt", serialize_with="super::serialize_ts_opt")]
+    pub time: Option<DateTime<Utc>>,
+    /// Time that this match actually started, or `None` if it has not been played
+    #[serde(deserialize_with="super::deserialize_ts_opt", serialize_with="super::serialize_ts_opt")]
+    pub actual_time: Option<DateTime<Utc>>,
+    /// Start time of this match as predicted by TBA from the progress of the event
+    #[serde(deserialize_with="super::deserialize_ts_opt", serialize_with="super::serialize_ts_opt")]
+    pub predicted_time: Option<DateTime<Utc>>,
+    /// Time that the results of this match were posted
+    #[serde(deserialize_with="super::deserialize_ts_opt", serialize_with="super::serialize_ts_opt")]
+    pub post_result_time: Option<DateTime<Utc>>,
+    /// Season-specific breakdown of each alliance's score, keyed by alliance color
+    pub score_breakdown: Option<serde_json::Value>,
     pub videos: Vec<MatchVideo>,
 }
 
-#[derive(Debug,Clone,Copy,)]
-pub enum MatchVideoType {
-    Youtube,
-    TBA,
+/// An event's matches grouped by competition level, with each level in the order its matches
+/// are played
+#[derive(Clone, Debug, Default)]
+pub struct Schedule {
+    levels: BTreeMap<PlayoffLevel, Vec<Match>>,
+}
+
+string_enum! {
+    pub enum MatchVideoType ("match video type") {
+        Youtube = "youtube",
+        TBA = "tba",
+    }
 }
 
-#[derive(Debug,Clone,Deserialize)]
+#[derive(Debug,Clone,Deserialize,Serialize)]
 pub struct MatchVideo {
     #[serde(rename="type")]
     pub type_: MatchVideoType,
     pub key: String,
 }
 
-#[derive(Clone,Debug,Deserialize)]
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct MatchAlliances {
     pub red: MatchAlliance,
     pub blue: MatchAlliance
 }
 
-#[derive(Clone,Debug,Deserialize)]
+impl MatchAlliances {
+    /// Get the alliance of the given color
+    pub fn get(&self, color: AllianceColor) -> &MatchAlliance {
+        match color {
+            AllianceColor::Red => &self.red,
+            AllianceColor::Blue => &self.blue,
+        }
+    }
+}
+
+#[derive(Clone,Debug,Deserialize,Serialize)]
 pub struct MatchAlliance {
     pub score: Option<i32>,
     pub team_keys: Vec<TeamKey>,
@@ -69,29 +244,211 @@ pub struct MatchAlliance {
 }
 
 
-impl<'de> Deserialize<'de> for MatchWinner {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: serde::Deserializer<'de> {
-        let str = <&str as Deserialize<'de>>::deserialize(deserializer)?;
-        match str {
-            "red" => Ok(Self::Red),
-            "blue" => Ok(Self::Blue),
-            "" => Ok(Self::Tie),
-            _ => Err(serde::de::Error::custom(format!("Unknown match winner string: '{}'", str)))
+impl Match {
+    /// Compare two matches by the order they are played in: by event key, then competition
+    /// level, then match number, then set number. Playoff series alternate between sets, so
+    /// e.g. `qf2m1` is played before `qf1m2`
+    pub fn cmp_play_order(&self, other: &Self) -> Ordering {
+        self.event_key.as_ref().cmp(other.event_key.as_ref())
+            .then(self.comp_level.cmp(&other.comp_level))
+            .then(self.match_number.cmp(&other.match_number))
+            .then(self.set_number.cmp(&other.set_number))
+    }
+
+    /// Get the scheduled start time of this match in the local time of the event's venue
+    pub fn local_time(&self, event: &Event) -> Option<DateTime<Tz>> {
+        event.local_time(self.time?)
+    }
+
+    /// Get the actual start time of this match in the local time of the event's venue
+    pub fn local_actual_time(&self, event: &Event) -> Option<DateTime<Tz>> {
+        event.local_time(self.actual_time?)
+    }
+
+    /// Get the predicted start time of this match in the local time of the event's venue
+    pub fn local_predicted_time(&self, event: &Event) -> Option<DateTime<Tz>> {
+        event.local_time(self.predicted_time?)
+    }
+
+    /// Get the time the results of this match were posted in the local time of the event's venue
+    pub fn local_post_result_time(&self, event: &Event) -> Option<DateTime<Tz>> {
+        event.local_time(self.post_result_time?)
+    }
+
+    /// Check if both alliances have a score posted for this match. Unplayed matches have no
+    /// score or a score of -1
+    pub fn is_played(&self) -> bool {
+        [&self.alliances.red, &self.alliances.blue]
+            .iter()
+            .all(|alliance| alliance.score.is_some_and(|score| score >= 0))
+    }
+}
+
+impl Schedule {
+    /// Group the given matches by competition level and sort them into play order
+    pub fn new(matches: impl IntoIterator<Item = Match>) -> Self {
+        let mut levels = BTreeMap::<_, Vec<_>>::new();
+        for m in matches {
+            levels.entry(m.comp_level.clone()).or_default().push(m);
+        }
+        for matches in levels.values_mut() {
+            matches.sort_by(Match::cmp_play_order);
         }
+
+        Self { levels }
+    }
+
+    /// Get the matches of the given competition level in play order
+    pub fn level(&self, level: PlayoffLevel) -> &[Match] {
+        self.levels.get(&level).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterate over every competition level with matches in this schedule, in play order
+    pub fn levels(&self) -> impl Iterator<Item = (&PlayoffLevel, &[Match])> {
+        self.levels.iter().map(|(level, matches)| (level, matches.as_slice()))
+    }
+
+    /// Iterate over all matches in this schedule in play order
+    pub fn iter(&self) -> impl Iterator<Item = &Match> {
+        self.levels.values().flatten()
+    }
+
+    /// Get the most recent match in play order that has a posted score
+    pub fn last_played(&self) -> Option<&Match> {
+        self.iter().filter(|m| m.is_played()).last()
+    }
+
+    /// Get the first match without a posted score after the last played match
+    pub fn next_unplayed(&self) -> Option<&Match> {
+        let last_played = self
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.is_played())
+            .last()
+            .map(|(i, _)| i);
+        self
+            .iter()
+            .skip(last_played.map_or(0, |i| i + 1))
+            .find(|m| !m.is_played())
     }
 }
 
-impl<'de> Deserialize<'de> for MatchVideoType {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: serde::Deserializer<'de> {
-        let str = <&str as Deserialize<'de>>::deserialize(deserializer)?;
-        match str {
-            "youtube" => Ok(Self::Youtube),
-            "tba" => Ok(Self::TBA),
-            _ => Err(serde::de::Error::custom(format!("Unknown match video type '{}'", str)))
+/// Builders for the teams and matches used in tests
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::*;
+
+    /// Get the key of the team with the given number
+    pub(crate) fn team(number: u32) -> TeamKey {
+        format!("frc{}", number).parse().unwrap()
+    }
+
+    /// Get the keys of the teams with the given numbers
+    pub(crate) fn teams(numbers: impl IntoIterator<Item = u32>) -> Vec<TeamKey> {
+        numbers.into_iter().map(team).collect()
+    }
+
+    /// Get the recorded qualification match 1 of 2023cafr
+    pub(crate) fn fixture() -> Match {
+        serde_json::from_str(include_str!("../../fixtures/match_2023cafr_qm1.json")).unwrap()
+    }
+
+    /// Get a match with the given key and alliances and the `(red, blue)` scores, or an unplayed
+    /// match if there are no scores. The event, competition level and numbers are taken from
+    /// the key and the winner from the scores, while the score breakdown is kept from
+    /// [fixture]. Times are left out, so matches of the same season are replayed in key order
+    pub(crate) fn match_with(key: &str, red: &[TeamKey], blue: &[TeamKey], scores: Option<(i32, i32)>) -> Match {
+        let key: MatchKey = key.parse().unwrap();
+        let alliance = |teams: &[TeamKey], score| MatchAlliance {
+            score,
+            team_keys: teams.to_vec(),
+            surrogate_team_keys: Vec::new(),
+            dq_team_keys: Vec::new(),
+        };
+        Match {
+            event_key: key.event(),
+            comp_level: key.comp_level(),
+            set_number: key.set_number(),
+            match_number: key.match_number(),
+            alliances: MatchAlliances {
+                red: alliance(red, scores.map(|(red, _)| red)),
+                blue: alliance(blue, scores.map(|(_, blue)| blue)),
+            },
+            winning_alliance: match scores.map(|(red, blue)| red.cmp(&blue)) {
+                Some(Ordering::Greater) => MatchWinner::Red,
+                Some(Ordering::Less) => MatchWinner::Blue,
+                _ => MatchWinner::Tie,
+            },
+            time: None,
+            actual_time: None,
+            predicted_time: None,
+            post_result_time: None,
+            key,
+            ..fixture()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_keys() {
+        let event: EventKey = "2023cafr".parse().unwrap();
+        let key = MatchKey::new(&event, PlayoffLevel::SF, 2, 1).unwrap();
+        assert_eq!(key.to_string(), "2023cafr_sf2m1");
+        assert_eq!((key.event(), key.comp_level(), key.set_number(), key.match_number()), (event.clone(), PlayoffLevel::SF, 2, 1));
+        assert_eq!(MatchKey::new(&event, PlayoffLevel::QM, 5, 12).unwrap().to_string(), "2023cafr_qm12");
+        assert_eq!(MatchKey::new(&event, PlayoffLevel::Unknown("xx".to_owned()), 1, 1).unwrap().comp_level().as_str(), "xx");
+        for level in ["", "QF", "ef2", "q_f"] {
+            let level = PlayoffLevel::Unknown(level.to_owned());
+            assert!(matches!(MatchKey::new(&event, level, 1, 1), Err(ParseKeyError::CompLevel(_))));
+        }
+    }
+
+    #[test]
+    fn play_order() {
+        let mut keys = ["2023cafr_qm10", "2023cafr_f1m1", "2023cafr_sf2m1", "2023cafr_qm2"]
+            .map(|k| k.parse::<MatchKey>().unwrap());
+        keys.sort();
+        assert_eq!(
+            keys.map(|k| k.to_string()),
+            ["2023cafr_qm2", "2023cafr_qm10", "2023cafr_sf2m1", "2023cafr_f1m1"]
+        );
+        let mut keys = ["2023cafr_qf1m2", "2023cafr_qf2m1", "2023cafr_qf1m1"].map(|k| k.parse::<MatchKey>().unwrap());
+        keys.sort();
+        assert_eq!(keys.clone().map(|k| k.to_string()), ["2023cafr_qf1m1", "2023cafr_qf2m1", "2023cafr_qf1m2"]);
+        keys.sort_by(MatchKey::cmp_by_set);
+        assert_eq!(keys.map(|k| k.to_string()), ["2023cafr_qf1m1", "2023cafr_qf1m2", "2023cafr_qf2m1"]);
+
+        let template = test_util::fixture();
+        let (red, blue) = (&template.alliances.red.team_keys, &template.alliances.blue.team_keys);
+        let mut matches = ["2023cafr_qf2m1", "2023cafr_qf1m2", "2023cafr_qm12", "2023cafr_qf1m1"]
+            .map(|key| test_util::match_with(key, red, blue, Some((47, 61))));
+        matches[1].alliances.red.score = Some(-1);
+        let schedule = Schedule::new(matches);
+        assert_eq!(
+            schedule.iter().map(|m| m.key.to_string()).collect::<Vec<_>>(),
+            ["2023cafr_qm12", "2023cafr_qf1m1", "2023cafr_qf2m1", "2023cafr_qf1m2"]
+        );
+        assert_eq!(schedule.level(PlayoffLevel::QM).len(), 1);
+        assert_eq!(schedule.last_played().unwrap().key.to_string(), "2023cafr_qf2m1");
+        assert_eq!(schedule.next_unplayed().unwrap().key.to_string(), "2023cafr_qf1m2");
+    }
+
+    #[test]
+    fn unplayed_local_time() {
+        let mut json: serde_json::Value = serde_json::from_str(include_str!("../../fixtures/match_2023cafr_qm1.json")).unwrap();
+        for field in ["actual_time", "post_result_time"] {
+            json[field] = serde_json::Value::Null;
         }
+        let m: Match = serde_json::from_value(json).unwrap();
+        let event: Event = serde_json::from_str(include_str!("../../fixtures/event_2023cafr.json")).unwrap();
+        assert!(m.local_actual_time(&event).is_none());
+        assert_eq!(
+            m.local_time(&event).unwrap().to_rfc3339(),
+            "2023-03-16T09:26:00-07:00"
+        );
     }
 }
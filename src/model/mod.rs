@@ -1,17 +1,131 @@
-use chrono::{NaiveDate, NaiveDateTime};
-use serde::{Deserializer, Deserialize};
+use std::cell::Cell;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserializer, Deserialize, Serialize, Serializer};
+
+/// Define an enum of the string values used for a field in the API, with an `Unknown` variant
+/// that keeps values added to the API after this crate was written
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident ($what:literal) {
+            $($(#[$vmeta:meta])* $variant:ident = $str:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($(#[$vmeta])* $variant,)+
+            /// A value that is not known to this crate, as returned by the API
+            Unknown(String),
+        }
+
+        impl $name {
+            /// Get the string used to identify this value in the API
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $str,)+
+                    Self::Unknown(s) => s,
+                }
+            }
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = ::std::convert::Infallible;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(match s {
+                    $($str => Self::$variant,)+
+                    _ => Self::Unknown(s.to_owned()),
+                })
+            }
+        }
+
+        impl ::serde::Serialize for $name {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let str = <String as ::serde::Deserialize>::deserialize(deserializer)?;
+                match str.parse() {
+                    Ok(Self::Unknown(str)) => $crate::model::unknown($what, &str, Self::Unknown(str.clone())),
+                    Ok(val) => Ok(val),
+                    Err(e) => match e {},
+                }
+            }
+        }
+    };
+}
+
+/// Define an enum of the integer values used for a field in the API, with an `Unknown` variant
+/// that keeps values added to the API after this crate was written
+macro_rules! int_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident ($what:literal) {
+            $($(#[$vmeta:meta])* $variant:ident = $code:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($(#[$vmeta])* $variant,)+
+            /// A value that is not known to this crate, as returned by the API
+            Unknown(i64),
+        }
+
+        impl $name {
+            /// Get the number used to identify this value in the API
+            pub const fn code(&self) -> i64 {
+                match self {
+                    $(Self::$variant => $code,)+
+                    Self::Unknown(code) => *code,
+                }
+            }
+
+            /// Get the value identified by the given number in the API
+            pub const fn from_code(code: i64) -> Self {
+                match code {
+                    $($code => Self::$variant,)+
+                    _ => Self::Unknown(code),
+                }
+            }
+        }
+
+        impl ::serde::Serialize for $name {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_i64(self.code())
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let code = <i64 as ::serde::Deserialize>::deserialize(deserializer)?;
+                match Self::from_code(code) {
+                    Self::Unknown(code) => $crate::model::unknown($what, code, Self::Unknown(code)),
+                    val => Ok(val),
+                }
+            }
+        }
+    };
+}
 
 pub mod id;
 pub mod team;
 pub mod event;
 pub mod matches;
+pub mod award;
+pub mod season;
 
-#[derive(Clone,Copy,PartialEq,Eq,Hash,Debug,Deserialize)]
+#[derive(Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash,Debug,Deserialize,Serialize)]
 #[serde(transparent)]
 pub struct Year(u16);
 
 /// Error returned from [Year::new] if the given value was not 4 digits
-#[derive(Clone, Copy,Debug)]
+#[derive(Clone, Copy,Debug,thiserror::Error)]
+#[error("Year must be a 4 digit number")]
 pub struct Not4Digits;
 
 impl Year {
@@ -22,10 +136,46 @@ impl Year {
             _ => Err(Not4Digits)
         }
     }
+
+    /// Get the year number from this wrapper structure
+    pub const fn val(&self) -> u16 {
+        self.0
+    }
+}
+
+thread_local! {
+    static STRICT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f`, rejecting enum values that are not known to this crate while deserializing if
+/// `strict` is set, instead of keeping them as `Unknown` variants
+pub(crate) fn with_strictness<T>(strict: bool, f: impl FnOnce() -> T) -> T {
+    struct Reset(bool);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            STRICT.with(|s| s.set(self.0));
+        }
+    }
+
+    let _reset = Reset(STRICT.with(|s| s.replace(strict)));
+    f()
+}
+
+/// Check if enum values that are not known to this crate are currently being rejected
+pub(crate) fn is_strict() -> bool {
+    STRICT.with(Cell::get)
+}
+
+/// Keep an unknown enum value as `fallback`, or fail if deserializing strictly
+pub(crate) fn unknown<T, E: serde::de::Error>(what: &str, value: impl std::fmt::Display, fallback: T) -> Result<T, E> {
+    match is_strict() {
+        true => Err(E::custom(format!("Unknown {} '{}'", what, value))),
+        false => Ok(fallback),
+    }
 }
 
 pub fn deserialize_yyyymmdd_opt<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
-    let str = Option::<&str>::deserialize(deserializer)?;
+    let str = Option::<String>::deserialize(deserializer)?;
     str
         .map(|str| NaiveDate::parse_from_str(&str, "%Y-%m-%d")
             .map_err(serde::de::Error::custom)
@@ -35,14 +185,98 @@ pub fn deserialize_yyyymmdd_opt<'de, D: Deserializer<'de>>(deserializer: D) -> R
 
 
 pub fn deserialize_yyyymmdd<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
-    let str = <&str as Deserialize>::deserialize(deserializer)?;
+    let str = String::deserialize(deserializer)?;
     NaiveDate::parse_from_str(&str, "%Y-%m-%d")
         .map_err(serde::de::Error::custom)
 }
 
-pub fn deserialize_ts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+pub fn deserialize_ts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
     let n = <i64 as Deserialize>::deserialize(deserializer)?;
-    Ok(NaiveDateTime::from_timestamp(n, 0))
+    DateTime::from_timestamp(n, 0)
+        .ok_or_else(|| serde::de::Error::custom(format!("Timestamp {} is out of range", n)))
+}
+
+pub fn deserialize_ts_opt<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<i64>::deserialize(deserializer)?
+        .map(|n| DateTime::from_timestamp(n, 0)
+            .ok_or_else(|| serde::de::Error::custom(format!("Timestamp {} is out of range", n)))
+        )
+        .transpose()
+}
+
+pub fn serialize_yyyymmdd_opt<S: Serializer>(date: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error> {
+    date
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .serialize(serializer)
+}
+
+pub fn serialize_yyyymmdd<S: Serializer>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&date.format("%Y-%m-%d"))
+}
+
+pub fn serialize_ts<S: Serializer>(ts: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(ts.timestamp())
+}
+
+pub fn serialize_ts_opt<S: Serializer>(ts: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+    ts
+        .map(|ts| ts.timestamp())
+        .serialize(serializer)
+}
+
+/// Convert an HTML fragment returned by the API, such as a team status string, to plain text by
+/// removing its tags and decoding common character entities
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find(['<', '&']) {
+        text.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with('<') {
+            match rest.find('>') {
+                Some(end) => rest = &rest[end + 1..],
+                None => break,
+            }
+            continue
+        }
+
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 8)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match entity {
+            Some((c, end)) => {
+                text.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Decode the name of an HTML character entity, without the surrounding `&` and `;`
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = name.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code)
+        },
+    }
 }
 
 impl std::fmt::Display for Year {
@@ -50,3 +284,66 @@ impl std::fmt::Display for Year {
         self.0.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::Value;
+
+    use std::collections::HashMap;
+
+    use super::{award::Award, event::{Event, EventOPRs, EventRankings, PlayoffType, TeamEventStatus, WebCastType}, matches::{Match, MatchKey}, strip_html, team::{Team, TeamKey, TeamRobot}, with_strictness};
+
+    /// Deserialize a fixture into `T` and check that serializing it again produces the same JSON
+    fn round_trip<T: DeserializeOwned + Serialize>(fixture: &str) {
+        let original: Value = serde_json::from_str(fixture).unwrap();
+        let val: T = serde_json::from_str(fixture).unwrap();
+        assert_eq!(serde_json::to_value(&val).unwrap(), original);
+    }
+
+    #[test]
+    fn round_trip_fixtures() {
+        round_trip::<Team>(include_str!("../../fixtures/team_frc1280.json"));
+        round_trip::<Vec<TeamRobot>>(include_str!("../../fixtures/team_frc1280_robots.json"));
+        round_trip::<Event>(include_str!("../../fixtures/event_2023cafr.json"));
+        round_trip::<EventOPRs>(include_str!("../../fixtures/event_2023cafr_oprs.json"));
+        round_trip::<EventRankings>(include_str!("../../fixtures/event_2023cafr_rankings.json"));
+        round_trip::<Vec<Award>>(include_str!("../../fixtures/event_2023cafr_awards.json"));
+        round_trip::<Match>(include_str!("../../fixtures/match_2023cafr_qm1.json"));
+        round_trip::<HashMap<TeamKey, Option<TeamEventStatus>>>(include_str!("../../fixtures/event_2023cafr_team_statuses.json"));
+    }
+
+    #[test]
+    fn unknown_enum_values() {
+        let mut json: Value = serde_json::from_str(include_str!("../../fixtures/event_2023cafr.json")).unwrap();
+        json["playoff_type"] = 42.into();
+        json["webcasts"][0]["type"] = "holovid".into();
+
+        let event: Event = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(event.playoff_type, Some(PlayoffType::Unknown(42)));
+        assert_eq!(event.webcasts[0].type_, WebCastType::Unknown("holovid".to_owned()));
+        assert_eq!(serde_json::to_value(&event).unwrap(), json);
+
+        assert!(with_strictness(true, || serde_json::from_value::<Event>(json)).is_err());
+        assert!(serde_json::from_value::<MatchKey>("2023cafr_xx1m1".into()).is_ok());
+        assert!(with_strictness(true, || serde_json::from_value::<MatchKey>("2023cafr_xx1m1".into())).is_err());
+
+        // Codes the crate knew before the 2023 formats keep the variants they had
+        assert_eq!(PlayoffType::from_code(5), PlayoffType::DoubleElim8Team);
+        assert_eq!(PlayoffType::from_code(10), PlayoffType::ModernDoubleElim8Team);
+    }
+
+    #[test]
+    fn status_text() {
+        assert_eq!(strip_html("Team 1280 was <b>Rank 4/40</b> &amp; <i>picked</i>&#33; a&b"), "Team 1280 was Rank 4/40 & picked! a&b");
+
+        let statuses: HashMap<TeamKey, Option<TeamEventStatus>> =
+            serde_json::from_str(include_str!("../../fixtures/event_2023cafr_team_statuses.json")).unwrap();
+        let picked = statuses[&"frc1280".parse().unwrap()].as_ref().unwrap();
+        assert_eq!(picked.alliance_status_text().unwrap(), "Team 1280 was the Captain of Alliance 2.");
+        let unpicked = statuses[&"frc9999".parse().unwrap()].as_ref().unwrap();
+        assert!(unpicked.alliance.is_none());
+        assert!(unpicked.playoff_status_text().is_none());
+        assert!(statuses[&"frc8888".parse().unwrap()].is_none());
+    }
+}
@@ -0,0 +1,211 @@
+//! Summaries of a team's results over a whole season
+
+use futures_util::future::{try_join, try_join4, try_join_all};
+use serde::Serialize;
+
+use crate::{ctx::{endpoints::EndPoint, Context}, Error};
+
+use super::{
+    award::Award,
+    event::{Event, EventDistrictPoints, EventOPRs, EventDistrictPointsPoints, PlayoffLevel, PlayoffStatus, TeamEventStatus, WLTRecord},
+    matches::{Match, MatchWinner},
+    team::TeamKey,
+    Year,
+};
+
+/// How far a team progressed at an event, ordered from worst to best
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Finish {
+    /// The team did not play in the playoffs
+    Qualifications,
+    /// The team was eliminated at, or is still playing in, the given playoff level
+    Playoffs(PlayoffLevel),
+    Finalist,
+    Winner,
+}
+
+impl Finish {
+    /// Get how far a team progressed at an event from its status there
+    pub fn from_status(status: &TeamEventStatus) -> Self {
+        let Some(playoff) = status.playoff.as_ref() else { return Self::Qualifications };
+        match (&playoff.status, &playoff.level) {
+            (PlayoffStatus::Won, _) => Self::Winner,
+            (PlayoffStatus::Eliminated, PlayoffLevel::F) => Self::Finalist,
+            (_, level) => Self::Playoffs(level.clone()),
+        }
+    }
+}
+
+/// A team's results at a single event
+#[derive(Clone, Debug, Serialize)]
+pub struct TeamEventSummary {
+    pub event: Event,
+    pub status: Option<TeamEventStatus>,
+    pub qual_rank: Option<u32>,
+    /// Number of teams ranked at the event
+    pub num_teams: Option<u16>,
+    /// Record in every match the team played at the event, excluding matches it played as a
+    /// surrogate
+    pub record: WLTRecord,
+    pub opr: Option<f64>,
+    /// Awards won by the team at the event
+    pub awards: Vec<Award>,
+    pub district_points: Option<EventDistrictPointsPoints>,
+    pub finish: Finish,
+}
+
+impl TeamEventSummary {
+    /// Summarize a team's results at an event from the event's data
+    pub fn new(
+        team: &TeamKey,
+        event: Event,
+        status: Option<TeamEventStatus>,
+        matches: &[Match],
+        oprs: Option<&EventOPRs>,
+        awards: &[Award],
+        district_points: Option<&EventDistrictPoints>,
+    ) -> Self {
+        let (mut wins, mut losses, mut ties) = (0, 0, 0);
+        for m in matches.iter().filter(|m| m.is_played()) {
+            let alliances = [(&m.alliances.red, MatchWinner::Red), (&m.alliances.blue, MatchWinner::Blue)];
+            let Some((_, color)) = alliances
+                .into_iter()
+                .find(|(alliance, _)| alliance.team_keys.contains(team) && !alliance.surrogate_team_keys.contains(team))
+            else { continue };
+            match &m.winning_alliance {
+                winner if *winner == color => wins += 1,
+                MatchWinner::Red | MatchWinner::Blue => losses += 1,
+                _ => ties += 1,
+            }
+        }
+
+        let qual = status.as_ref().and_then(|status| status.qual.as_ref());
+        Self {
+            qual_rank: qual.and_then(|qual| qual.ranking.as_ref()?.rank),
+            num_teams: qual.and_then(|qual| qual.num_teams),
+            record: WLTRecord { wins: Some(wins), losses: Some(losses), ties: Some(ties) },
+            opr: oprs.and_then(|oprs| oprs.oprs.get(team).copied()),
+            awards: awards
+                .iter()
+                .filter(|award| award.recipient_list.iter().any(|recipient| recipient.team_key.as_ref() == Some(team)))
+                .cloned()
+                .collect(),
+            district_points: district_points.and_then(|points| points.points.get(team).cloned()),
+            finish: status.as_ref().map_or(Finish::Qualifications, Finish::from_status),
+            event,
+            status,
+        }
+    }
+}
+
+/// A team's results over every event it attended in a season
+#[derive(Clone, Debug, Serialize)]
+pub struct TeamSeason {
+    pub team: TeamKey,
+    pub year: Year,
+    /// Events in the order they started
+    pub events: Vec<TeamEventSummary>,
+    /// Record over every event
+    pub record: WLTRecord,
+    /// Best finish at any event, or `None` if the team attended no events
+    pub best_finish: Option<Finish>,
+    /// Average OPR over the events the team has an OPR at
+    pub avg_opr: Option<f64>,
+    pub peak_opr: Option<f64>,
+    /// Total district points earned over every event
+    pub district_points: i32,
+}
+
+impl TeamSeason {
+    /// Combine the summaries of a team's events in a season
+    pub fn new(team: TeamKey, year: Year, mut events: Vec<TeamEventSummary>) -> Self {
+        events.sort_by_key(|summary| summary.event.simple.start_date);
+        let sum = |field: fn(&WLTRecord) -> Option<u16>| events.iter().map(|summary| field(&summary.record).unwrap_or(0)).sum();
+        let oprs = events.iter().filter_map(|summary| summary.opr).collect::<Vec<_>>();
+        Self {
+            record: WLTRecord {
+                wins: Some(sum(|record| record.wins)),
+                losses: Some(sum(|record| record.losses)),
+                ties: Some(sum(|record| record.ties)),
+            },
+            best_finish: events.iter().map(|summary| summary.finish.clone()).max(),
+            avg_opr: (!oprs.is_empty()).then(|| oprs.iter().sum::<f64>() / oprs.len() as f64),
+            peak_opr: oprs.iter().copied().reduce(f64::max),
+            district_points: events.iter().filter_map(|summary| Some(summary.district_points.as_ref()?.total)).sum(),
+            team,
+            year,
+            events,
+        }
+    }
+}
+
+impl TeamKey {
+    /// Summarize this team's results over a season, fetching the data of every event it
+    /// attended concurrently
+    pub async fn season_summary(&self, year: Year, ctx: &Context) -> Result<TeamSeason, Error> {
+        let (events, statuses) = try_join(
+            ctx.endpoints.team.events_by_year.get((self.clone(), year), ctx),
+            ctx.endpoints.team.event_statuses_by_year.get((self.clone(), year), ctx),
+        ).await?;
+
+        let summaries = try_join_all(events.iter().map(|event| {
+            let key = event.simple.key.clone();
+            let status = statuses.get(&key).cloned().flatten();
+            async move {
+                let (matches, oprs, awards, district_points) = try_join4(
+                    ctx.endpoints.event.matches.get((key.clone(),), ctx),
+                    ctx.endpoints.event.oprs.get((key.clone(),), ctx),
+                    ctx.endpoints.event.awards.get((key.clone(),), ctx),
+                    ctx.endpoints.event.district_points.get((key.clone(),), ctx),
+                ).await?;
+                Ok::<_, Error>(TeamEventSummary::new(
+                    self,
+                    event.clone(),
+                    status,
+                    &matches,
+                    (*oprs).as_ref(),
+                    &awards,
+                    (*district_points).as_ref(),
+                ))
+            }
+        })).await?;
+
+        Ok(TeamSeason::new(self.clone(), year, summaries))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn season_summary() {
+        let team = "frc1280".parse::<TeamKey>().unwrap();
+        let event: Event = serde_json::from_str(include_str!("../../fixtures/event_2023cafr.json")).unwrap();
+        let mut statuses: std::collections::HashMap<TeamKey, Option<TeamEventStatus>> =
+            serde_json::from_str(include_str!("../../fixtures/event_2023cafr_team_statuses.json")).unwrap();
+        let m: Match = serde_json::from_str(include_str!("../../fixtures/match_2023cafr_qm1.json")).unwrap();
+        let oprs: EventOPRs = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_oprs.json")).unwrap();
+        let awards: Vec<Award> = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_awards.json")).unwrap();
+
+        let status = statuses.remove(&team).flatten();
+        let summary = TeamEventSummary::new(&team, event, status, &[m], Some(&oprs), &awards, None);
+        assert_eq!(summary.qual_rank, Some(4));
+        assert_eq!(summary.num_teams, Some(40));
+        assert_eq!((summary.record.wins, summary.record.losses), (Some(0), Some(1)));
+        assert_eq!(summary.finish, Finish::Finalist);
+        assert_eq!(summary.awards.len(), 1);
+        assert!((summary.opr.unwrap() - 25.462).abs() < 1e-3);
+
+        let mut second = summary.clone();
+        second.opr = Some(35.);
+        second.finish = Finish::Winner;
+        second.district_points = Some(EventDistrictPointsPoints { total: 40, ..Default::default() });
+        let season = TeamSeason::new(team, summary.event.simple.year, vec![summary, second]);
+        assert_eq!(season.record.losses, Some(2));
+        assert_eq!(season.best_finish, Some(Finish::Winner));
+        assert_eq!(season.peak_opr, Some(35.));
+        assert!((season.avg_opr.unwrap() - 30.231).abs() < 1e-3);
+        assert_eq!(season.district_points, 40);
+    }
+}
@@ -1,8 +1,9 @@
-use serde::Deserialize;
-use url::Url;
-use crate::{ctx::endpoints::EndPoint, key};
+use std::str::FromStr;
 
-use super::{id::TeamNumber, Year};
+use serde::{Deserialize, Serialize};
+use crate::{ctx::{Context, endpoints::EndPoint}, key, Error};
+
+use super::{event::{Event, EventKey, EventType}, id::{parse_digits, ParseKeyError, TeamNumber}, Year};
 
 key!(TeamKey(String) -> Team => (self, ctx) with ctx
             .endpoints
@@ -12,17 +13,17 @@ key!(TeamKey(String) -> Team => (self, ctx) with ctx
             .await
 );
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(transparent)]
 pub struct RobotKey(String);
 
 /// Structure representing basic data about an FRC team, that can be upgraded using a
 /// [Context](crate::ctx::Context)
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct SimpleTeam {
     pub key: TeamKey,
     pub team_number: TeamNumber,
-    pub nickame: Option<String>,
+    pub nickname: Option<String>,
     pub name: String,
     pub city: Option<String>,
     pub state_prov: Option<String>,
@@ -30,32 +31,37 @@ pub struct SimpleTeam {
 }
 
 /// A team object containing more data than a [SimpleTeam]
-#[derive(Clone, Deserialize, Debug)]
+///
+/// URLs are kept exactly as returned by the API, as team websites are entered by hand and are
+/// not always valid URLs
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct Team {
     #[serde(flatten)]
     pub simple: SimpleTeam,
+    pub school_name: Option<String>,
     pub address: Option<String>,
     pub postal_code: Option<String>,
     pub gmaps_place_id: Option<String>,
-    pub gmaps_url: Option<Url>,
+    pub gmaps_url: Option<String>,
     pub lat: Option<f64>,
     pub lng: Option<f64>,
     pub location_name: Option<String>,
-    pub website: Option<Url>,
+    pub website: Option<String>,
     pub rookie_year: Option<Year>,
     pub motto: Option<String>,
     pub home_championship: Option<HomeChampionshipsList>,
 }
 
-/// A newtype containing a map of year numbers to the location of a home championship
+/// A newtype containing a map of years to the location of a team's home championship in that
+/// year
 #[derive(Clone, Debug,)]
 pub struct HomeChampionshipsList(
-    Vec<(u16, String)>
+    Vec<(Year, String)>
 );
 
 /// A robot that competed in a given [Year] with name and a [TeamKey] referencing the team that
 /// created this robot
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TeamRobot {
     pub year: Year,
     pub robot_name: String,
@@ -64,6 +70,95 @@ pub struct TeamRobot {
 }
 
 
+impl Team {
+    /// Get the location of this team's home championship in the given year, e.g. `Houston`
+    pub fn home_championship_for(&self, year: Year) -> Option<&str> {
+        self.home_championship.as_ref()?.get(year)
+    }
+
+    /// Get the keys of the championship events that this team's home championship in the given
+    /// year refers to, fetching the events of that year
+    pub async fn home_championship_events(&self, year: Year, ctx: &Context) -> Result<Vec<EventKey>, Error> {
+        let Some(list) = self.home_championship.as_ref() else { return Ok(Vec::new()) };
+        let events = ctx.endpoints.events.by_year.get((year,), ctx).await?;
+        Ok(list.resolve(year, &events))
+    }
+}
+
+impl HomeChampionshipsList {
+    /// Get the location of the home championship in the given year
+    pub fn get(&self, year: Year) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(y, _)| *y == year)
+            .map(|(_, location)| location.as_str())
+    }
+
+    /// Iterate over each year and the location of the home championship in that year
+    pub fn iter(&self) -> impl Iterator<Item = (Year, &str)> {
+        self.0.iter().map(|(year, location)| (*year, location.as_str()))
+    }
+
+    /// Get the keys of the championship and championship division events in `events` that are
+    /// held at the location of the home championship in the given year
+    pub fn resolve(&self, year: Year, events: &[Event]) -> Vec<EventKey> {
+        let Some(location) = self.get(year) else { return Vec::new() };
+        events
+            .iter()
+            .filter(|event| event.simple.year == year)
+            .filter(|event| matches!(event.simple.event_type, EventType::CmpFinals | EventType::CmpDivision))
+            .filter(|event| event
+                .simple
+                .city
+                .as_deref()
+                .is_some_and(|city| city.eq_ignore_ascii_case(location))
+            )
+            .map(|event| event.simple.key.clone())
+            .collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a HomeChampionshipsList {
+    type Item = (Year, &'a str);
+    type IntoIter = Box<dyn Iterator<Item = (Year, &'a str)> + 'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl TeamKey {
+    /// Get the number of the team this key refers to. Keys of the form `frc1678B`, used at
+    /// offseason events for a team's second robot, return the number of the original team
+    pub fn number(&self) -> TeamNumber {
+        let number = self.0
+            .trim_start_matches("frc")
+            .trim_end_matches(|c: char| c.is_ascii_uppercase());
+        TeamNumber::new(parse_digits(number).expect("Team key was validated when created"))
+    }
+}
+
+impl FromStr for TeamKey {
+    type Err = ParseKeyError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s
+            .strip_prefix("frc")
+            .ok_or_else(|| ParseKeyError::TeamPrefix(s.to_owned()))?;
+        let number = number
+            .strip_suffix(|c: char| c.is_ascii_uppercase())
+            .unwrap_or(number);
+        match parse_digits(number) {
+            Some(_) => Ok(Self(s.to_owned())),
+            None => Err(ParseKeyError::TeamNumber(s.to_owned())),
+        }
+    }
+}
+
+impl From<TeamNumber> for TeamKey {
+    fn from(number: TeamNumber) -> Self {
+        Self(format!("frc{}", number))
+    }
+}
+
 impl AsRef<SimpleTeam> for Team {
     fn as_ref(&self) -> &SimpleTeam {
         &self.simple
@@ -102,3 +197,39 @@ impl<'de> Deserialize<'de> for HomeChampionshipsList {
         deserializer.deserialize_map(MapVisitor)
     }
 }
+
+impl Serialize for HomeChampionshipsList {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in self.0.iter() {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn home_championship() {
+        let team: Team = serde_json::from_str(include_str!("../../fixtures/team_frc1280.json")).unwrap();
+        let year = Year::new(2023).unwrap();
+        assert_eq!(team.home_championship_for(year), Some("Houston"));
+        assert_eq!(team.home_championship_for(Year::new(2020).unwrap()), None);
+        assert_eq!(team.home_championship.as_ref().unwrap().iter().count(), 5);
+
+        let regional: Event = serde_json::from_str(include_str!("../../fixtures/event_2023cafr.json")).unwrap();
+        let mut division = regional.clone();
+        division.simple.key = "2023joh".parse().unwrap();
+        division.simple.event_type = EventType::CmpDivision;
+        division.simple.city = Some("Houston".to_owned());
+        let events = [regional, division];
+        let resolved = team.home_championship.as_ref().unwrap().resolve(year, &events);
+        assert_eq!(resolved, ["2023joh".parse::<EventKey>().unwrap()]);
+    }
+}
@@ -0,0 +1,4 @@
+//! Local mirrors of API data that can be queried without making requests
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
@@ -0,0 +1,596 @@
+//! SQLite mirror of the teams, events, matches, alliances, OPRs and statuses of a season, kept up
+//! to date by requests that only download the resources that have changed since the last sync
+
+use std::{collections::{HashMap, HashSet}, path::Path, sync::Arc};
+
+use futures_util::future::{try_join5, try_join_all};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    ctx::Context,
+    model::{
+        event::{EliminationAlliance, Event, EventKey, EventOPRs, TeamEventStatus},
+        matches::Match,
+        team::{Team, TeamKey},
+        Year,
+    },
+    Error,
+};
+
+/// Number of events whose resources are requested at the same time during a sync
+const CONCURRENT_EVENTS: usize = 8;
+
+/// Paths of the resources synced for each event, relative to the event
+const EVENT_RESOURCES: [&str; 5] = ["teams", "matches", "alliances", "oprs", "teams/statuses"];
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS etags (
+    resource TEXT PRIMARY KEY,
+    etag TEXT,
+    synced_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS teams (
+    key TEXT PRIMARY KEY,
+    team_number INTEGER NOT NULL,
+    nickname TEXT,
+    name TEXT NOT NULL,
+    city TEXT,
+    state_prov TEXT,
+    country TEXT,
+    school_name TEXT,
+    rookie_year INTEGER,
+    website TEXT
+);
+
+CREATE TABLE IF NOT EXISTS events (
+    key TEXT PRIMARY KEY,
+    year INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    event_code TEXT NOT NULL,
+    event_type INTEGER NOT NULL,
+    district TEXT,
+    city TEXT,
+    state_prov TEXT,
+    country TEXT,
+    start_date TEXT NOT NULL,
+    end_date TEXT NOT NULL,
+    week INTEGER,
+    playoff_type INTEGER
+);
+CREATE INDEX IF NOT EXISTS events_year ON events (year);
+
+CREATE TABLE IF NOT EXISTS event_teams (
+    event_key TEXT NOT NULL REFERENCES events (key) ON DELETE CASCADE,
+    team_key TEXT NOT NULL REFERENCES teams (key),
+    PRIMARY KEY (event_key, team_key)
+);
+
+CREATE TABLE IF NOT EXISTS matches (
+    key TEXT PRIMARY KEY,
+    event_key TEXT NOT NULL REFERENCES events (key) ON DELETE CASCADE,
+    comp_level TEXT NOT NULL,
+    set_number INTEGER NOT NULL,
+    match_number INTEGER NOT NULL,
+    red_score INTEGER,
+    blue_score INTEGER,
+    winning_alliance TEXT NOT NULL,
+    time INTEGER,
+    actual_time INTEGER,
+    post_result_time INTEGER
+);
+CREATE INDEX IF NOT EXISTS matches_event ON matches (event_key);
+
+CREATE TABLE IF NOT EXISTS match_teams (
+    match_key TEXT NOT NULL REFERENCES matches (key) ON DELETE CASCADE,
+    color TEXT NOT NULL,
+    station INTEGER NOT NULL,
+    team_key TEXT NOT NULL,
+    surrogate INTEGER NOT NULL,
+    dq INTEGER NOT NULL,
+    PRIMARY KEY (match_key, color, station)
+);
+CREATE INDEX IF NOT EXISTS match_teams_team ON match_teams (team_key);
+
+CREATE TABLE IF NOT EXISTS alliances (
+    event_key TEXT NOT NULL REFERENCES events (key) ON DELETE CASCADE,
+    number INTEGER NOT NULL,
+    name TEXT,
+    backup_in TEXT,
+    backup_out TEXT,
+    level TEXT,
+    status TEXT,
+    wins INTEGER,
+    losses INTEGER,
+    ties INTEGER,
+    PRIMARY KEY (event_key, number)
+);
+
+CREATE TABLE IF NOT EXISTS alliance_teams (
+    event_key TEXT NOT NULL,
+    number INTEGER NOT NULL,
+    pick INTEGER NOT NULL,
+    team_key TEXT NOT NULL,
+    PRIMARY KEY (event_key, number, pick),
+    FOREIGN KEY (event_key, number) REFERENCES alliances (event_key, number) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS oprs (
+    event_key TEXT NOT NULL REFERENCES events (key) ON DELETE CASCADE,
+    team_key TEXT NOT NULL,
+    opr REAL NOT NULL,
+    dpr REAL,
+    ccwm REAL,
+    PRIMARY KEY (event_key, team_key)
+);
+
+CREATE TABLE IF NOT EXISTS statuses (
+    event_key TEXT NOT NULL REFERENCES events (key) ON DELETE CASCADE,
+    team_key TEXT NOT NULL,
+    qual_rank INTEGER,
+    num_teams INTEGER,
+    wins INTEGER,
+    losses INTEGER,
+    ties INTEGER,
+    alliance_number INTEGER,
+    alliance_pick INTEGER,
+    playoff_level TEXT,
+    playoff_status TEXT,
+    overall_status TEXT,
+    PRIMARY KEY (event_key, team_key)
+);
+";
+
+/// Errors that can occur while reading or syncing a store
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Failed to fetch a resource from the API: {0}")]
+    Api(#[from] Error),
+}
+
+/// Resources requested during a sync
+#[derive(Clone, Debug, Default)]
+pub struct SyncReport {
+    /// Number of resources requested
+    pub checked: usize,
+    /// Paths of the resources that had changed and were rewritten, relative to the API root
+    pub changed: Vec<String>,
+}
+
+/// A new value and ETag of a resource, or `None` if it has not changed
+type Change<T> = Option<(Arc<T>, Option<String>)>;
+
+/// SQLite database with a normalized copy of API data, along with the ETag of each resource
+/// its rows were written from
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Open the database at the given path, creating it and its tables if they do not exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Open a database that is kept in memory and dropped along with the store
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, StoreError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Get the connection to the database, to query the mirrored data
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Get the ETag the rows of a resource were written from, or `None` if the resource has not
+    /// been synced or the API did not send an ETag for it
+    pub fn etag(&self, resource: &str) -> Result<Option<String>, StoreError> {
+        Ok(self.conn
+            .query_row("SELECT etag FROM etags WHERE resource = ?1", [resource], |row| row.get(0))
+            .optional()?
+            .flatten())
+    }
+
+    /// Bring the events of a year, and the teams, matches, alliances, OPRs and statuses of each
+    /// event, up to date with the API. Resources that have the same ETag as when they were last
+    /// synced are not downloaded or rewritten, and each changed resource replaces every row
+    /// written from its previous version
+    pub async fn sync(&mut self, year: Year, ctx: &Context) -> Result<SyncReport, StoreError> {
+        let etags = self.etags()?;
+        let mut report = SyncReport::default();
+
+        let resource = format!("events/{}", year.val());
+        let events = ctx.endpoints.events.by_year
+            .get_if_changed((year,), etags.get(&resource).map(String::as_str), ctx)
+            .await?;
+        let tx = self.conn.transaction()?;
+        apply(&tx, &mut report, resource, events, |tx, events| write_events(tx, year, events))?;
+        tx.commit()?;
+
+        let keys = self.event_keys(year)?;
+        for chunk in keys.chunks(CONCURRENT_EVENTS) {
+            let changes = try_join_all(chunk.iter().map(|key| fetch_event(key, &etags, ctx))).await?;
+            let tx = self.conn.transaction()?;
+            for (key, (teams, matches, alliances, oprs, statuses)) in chunk.iter().zip(changes) {
+                let resource = |resource| event_resource(key.as_ref(), resource);
+                apply(&tx, &mut report, resource("teams"), teams, |tx, teams| write_teams(tx, key, teams))?;
+                apply(&tx, &mut report, resource("matches"), matches, |tx, matches| write_matches(tx, key, matches))?;
+                apply(&tx, &mut report, resource("alliances"), alliances, |tx, alliances| write_alliances(tx, key, alliances))?;
+                apply(&tx, &mut report, resource("oprs"), oprs, |tx, oprs| write_oprs(tx, key, oprs.as_ref()))?;
+                apply(&tx, &mut report, resource("teams/statuses"), statuses, |tx, statuses| write_statuses(tx, key, statuses))?;
+            }
+            tx.commit()?;
+        }
+
+        Ok(report)
+    }
+
+    /// Get the stored ETag of every resource
+    fn etags(&self) -> rusqlite::Result<HashMap<String, String>> {
+        let mut stmt = self.conn.prepare("SELECT resource, etag FROM etags WHERE etag IS NOT NULL")?;
+        let etags = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect();
+        etags
+    }
+
+    /// Get the keys of the stored events of a year
+    fn event_keys(&self, year: Year) -> Result<Vec<EventKey>, StoreError> {
+        let mut stmt = self.conn.prepare("SELECT key FROM events WHERE year = ?1 ORDER BY key")?;
+        let keys = stmt
+            .query_map([year.val()], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        // Keys were validated before they were written
+        Ok(keys.into_iter().filter_map(|key| key.parse().ok()).collect())
+    }
+}
+
+/// Request every synced resource of an event, sending the ETags they were last synced with
+async fn fetch_event(
+    key: &EventKey,
+    etags: &HashMap<String, String>,
+    ctx: &Context,
+) -> Result<(Change<Vec<Team>>, Change<Vec<Match>>, Change<Vec<EliminationAlliance>>, Change<Option<EventOPRs>>, Change<HashMap<TeamKey, Option<TeamEventStatus>>>), Error> {
+    let etag = |resource| etags.get(&event_resource(key.as_ref(), resource)).map(String::as_str);
+    let endpoints = &ctx.endpoints.event;
+    try_join5(
+        endpoints.teams.get_if_changed((key.clone(),), etag("teams"), ctx),
+        endpoints.matches.get_if_changed((key.clone(),), etag("matches"), ctx),
+        endpoints.alliances.get_if_changed((key.clone(),), etag("alliances"), ctx),
+        endpoints.oprs.get_if_changed((key.clone(),), etag("oprs"), ctx),
+        endpoints.team_statuses.get_if_changed((key.clone(),), etag("teams/statuses"), ctx),
+    ).await
+}
+
+/// Rewrite the rows of a resource if it has changed, and record its new ETag
+fn apply<T>(
+    tx: &Connection,
+    report: &mut SyncReport,
+    resource: String,
+    change: Change<T>,
+    write: impl FnOnce(&Connection, &T) -> rusqlite::Result<()>,
+) -> rusqlite::Result<()> {
+    report.checked += 1;
+    let Some((val, etag)) = change else { return Ok(()) };
+    write(tx, &val)?;
+    set_etag(tx, &resource, etag.as_deref())?;
+    report.changed.push(resource);
+    Ok(())
+}
+
+fn event_resource(event: &str, resource: &str) -> String {
+    format!("event/{}/{}", event, resource)
+}
+
+fn set_etag(tx: &Connection, resource: &str, etag: Option<&str>) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO etags (resource, etag, synced_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT (resource) DO UPDATE SET etag = excluded.etag, synced_at = excluded.synced_at",
+        params![resource, etag, chrono::Utc::now().timestamp()],
+    )?;
+    Ok(())
+}
+
+/// Replace the events of a year, removing the rows and ETags of events no longer in the year
+fn write_events(tx: &Connection, year: Year, events: &[Event]) -> rusqlite::Result<()> {
+    let keys = events.iter().map(|event| event.simple.key.as_ref()).collect::<HashSet<_>>();
+    let stored = tx
+        .prepare("SELECT key FROM events WHERE year = ?1")?
+        .query_map([year.val()], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for key in stored.iter().filter(|key| !keys.contains(key.as_str())) {
+        tx.execute("DELETE FROM events WHERE key = ?1", [key])?;
+        for resource in EVENT_RESOURCES {
+            tx.execute("DELETE FROM etags WHERE resource = ?1", [event_resource(key, resource)])?;
+        }
+    }
+
+    let mut stmt = tx.prepare(
+        "INSERT INTO events (key, year, name, event_code, event_type, district, city, state_prov, country, start_date, end_date, week, playoff_type)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+         ON CONFLICT (key) DO UPDATE SET
+            year = excluded.year, name = excluded.name, event_code = excluded.event_code,
+            event_type = excluded.event_type, district = excluded.district, city = excluded.city,
+            state_prov = excluded.state_prov, country = excluded.country, start_date = excluded.start_date,
+            end_date = excluded.end_date, week = excluded.week, playoff_type = excluded.playoff_type",
+    )?;
+    for event in events {
+        let simple = &event.simple;
+        stmt.execute(params![
+            simple.key.as_ref(),
+            simple.year.val(),
+            simple.name,
+            simple.event_code,
+            simple.event_type.code(),
+            simple.district.as_ref().map(|district| &district.key),
+            simple.city,
+            simple.state_prov,
+            simple.country,
+            simple.start_date.to_string(),
+            simple.end_date.to_string(),
+            event.week,
+            event.playoff_type.map(|playoff_type| playoff_type.code()),
+        ])?;
+    }
+    Ok(())
+}
+
+/// Replace the teams attending an event, updating the rows of teams that are already stored
+fn write_teams(tx: &Connection, event: &EventKey, teams: &[Team]) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM event_teams WHERE event_key = ?1", [event.as_ref()])?;
+    let mut team_stmt = tx.prepare(
+        "INSERT INTO teams (key, team_number, nickname, name, city, state_prov, country, school_name, rookie_year, website)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT (key) DO UPDATE SET
+            team_number = excluded.team_number, nickname = excluded.nickname, name = excluded.name,
+            city = excluded.city, state_prov = excluded.state_prov, country = excluded.country,
+            school_name = excluded.school_name, rookie_year = excluded.rookie_year, website = excluded.website",
+    )?;
+    let mut event_stmt = tx.prepare("INSERT OR IGNORE INTO event_teams (event_key, team_key) VALUES (?1, ?2)")?;
+    for team in teams {
+        let simple = &team.simple;
+        team_stmt.execute(params![
+            simple.key.as_ref(),
+            simple.team_number.val(),
+            simple.nickname,
+            simple.name,
+            simple.city,
+            simple.state_prov,
+            simple.country,
+            team.school_name,
+            team.rookie_year.map(|year| year.val()),
+            team.website,
+        ])?;
+        event_stmt.execute([event.as_ref(), simple.key.as_ref()])?;
+    }
+    Ok(())
+}
+
+/// Replace the matches of an event and the teams that played in them
+fn write_matches(tx: &Connection, event: &EventKey, matches: &[Match]) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM matches WHERE event_key = ?1", [event.as_ref()])?;
+    let mut match_stmt = tx.prepare(
+        "INSERT INTO matches (key, event_key, comp_level, set_number, match_number, red_score, blue_score, winning_alliance, time, actual_time, post_result_time)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?;
+    let mut team_stmt = tx.prepare(
+        "INSERT INTO match_teams (match_key, color, station, team_key, surrogate, dq) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for m in matches {
+        match_stmt.execute(params![
+            m.key.as_ref(),
+            event.as_ref(),
+            m.comp_level.as_str(),
+            m.set_number,
+            m.match_number,
+            m.alliances.red.score,
+            m.alliances.blue.score,
+            m.winning_alliance.as_str(),
+            m.time.map(|time| time.timestamp()),
+            m.actual_time.map(|time| time.timestamp()),
+            m.post_result_time.map(|time| time.timestamp()),
+        ])?;
+        for (color, alliance) in [("red", &m.alliances.red), ("blue", &m.alliances.blue)] {
+            for (station, team) in alliance.team_keys.iter().enumerate() {
+                team_stmt.execute(params![
+                    m.key.as_ref(),
+                    color,
+                    station + 1,
+                    team.as_ref(),
+                    alliance.surrogate_team_keys.contains(team),
+                    alliance.dq_team_keys.contains(team),
+                ])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replace the playoff alliances of an event, numbering them from 1 in the order they are given
+fn write_alliances(tx: &Connection, event: &EventKey, alliances: &[EliminationAlliance]) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM alliances WHERE event_key = ?1", [event.as_ref()])?;
+    let mut alliance_stmt = tx.prepare(
+        "INSERT INTO alliances (event_key, number, name, backup_in, backup_out, level, status, wins, losses, ties)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    let mut team_stmt = tx.prepare("INSERT INTO alliance_teams (event_key, number, pick, team_key) VALUES (?1, ?2, ?3, ?4)")?;
+    for (i, alliance) in alliances.iter().enumerate() {
+        let backup = alliance.backup.as_ref();
+        let status = alliance.status.as_ref();
+        let record = status.and_then(|status| status.record.as_ref());
+        alliance_stmt.execute(params![
+            event.as_ref(),
+            i + 1,
+            alliance.name,
+            backup.and_then(|backup| backup.in_.as_ref()).map(AsRef::<str>::as_ref),
+            backup.and_then(|backup| backup.out.as_ref()).map(AsRef::<str>::as_ref),
+            status.map(|status| status.level.as_str()),
+            status.map(|status| status.status.as_str()),
+            record.and_then(|record| record.wins),
+            record.and_then(|record| record.losses),
+            record.and_then(|record| record.ties),
+        ])?;
+        for (pick, team) in alliance.picks.iter().enumerate() {
+            team_stmt.execute(params![event.as_ref(), i + 1, pick, team.as_ref()])?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace the OPRs of an event, removing them if they have not been calculated
+fn write_oprs(tx: &Connection, event: &EventKey, oprs: Option<&EventOPRs>) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM oprs WHERE event_key = ?1", [event.as_ref()])?;
+    let Some(oprs) = oprs else { return Ok(()) };
+    let mut stmt = tx.prepare("INSERT INTO oprs (event_key, team_key, opr, dpr, ccwm) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    for (team, opr) in oprs.oprs.iter() {
+        stmt.execute(params![event.as_ref(), team.as_ref(), opr, oprs.dprs.get(team), oprs.ccwms.get(team)])?;
+    }
+    Ok(())
+}
+
+/// Replace the statuses of the teams at an event, skipping teams without a status
+fn write_statuses(tx: &Connection, event: &EventKey, statuses: &HashMap<TeamKey, Option<TeamEventStatus>>) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM statuses WHERE event_key = ?1", [event.as_ref()])?;
+    let mut stmt = tx.prepare(
+        "INSERT INTO statuses (event_key, team_key, qual_rank, num_teams, wins, losses, ties, alliance_number, alliance_pick, playoff_level, playoff_status, overall_status)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+    )?;
+    for (team, status) in statuses.iter() {
+        let Some(status) = status else { continue };
+        let qual = status.qual.as_ref();
+        let ranking = qual.and_then(|qual| qual.ranking.as_ref());
+        let record = ranking.and_then(|ranking| ranking.record.as_ref());
+        stmt.execute(params![
+            event.as_ref(),
+            team.as_ref(),
+            ranking.and_then(|ranking| ranking.rank),
+            qual.and_then(|qual| qual.num_teams),
+            record.and_then(|record| record.wins),
+            record.and_then(|record| record.losses),
+            record.and_then(|record| record.ties),
+            status.alliance.as_ref().map(|alliance| alliance.number),
+            status.alliance.as_ref().map(|alliance| alliance.pick),
+            status.playoff.as_ref().map(|playoff| playoff.level.as_str()),
+            status.playoff.as_ref().map(|playoff| playoff.status.as_str()),
+            status.overall_status_str,
+        ])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ctx::mock::MockApi;
+
+    use super::*;
+
+    #[test]
+    fn write_event() {
+        let store = Store::open_in_memory().unwrap();
+        let conn = store.connection();
+        let event: Event = serde_json::from_str(include_str!("../../fixtures/event_2023cafr.json")).unwrap();
+        let team: Team = serde_json::from_str(include_str!("../../fixtures/team_frc1280.json")).unwrap();
+        let m: Match = serde_json::from_str(include_str!("../../fixtures/match_2023cafr_qm1.json")).unwrap();
+        let oprs: EventOPRs = serde_json::from_str(include_str!("../../fixtures/event_2023cafr_oprs.json")).unwrap();
+        let statuses: HashMap<TeamKey, Option<TeamEventStatus>> =
+            serde_json::from_str(include_str!("../../fixtures/event_2023cafr_team_statuses.json")).unwrap();
+        let alliances: Vec<EliminationAlliance> = serde_json::from_value(serde_json::json!([
+            { "name": "Alliance 1", "backup": null, "declined": [], "picks": ["frc1678", "frc1280", "frc5430"], "status": null },
+        ])).unwrap();
+        let key = event.simple.key.clone();
+        let year = event.simple.year;
+
+        write_events(conn, year, &[event]).unwrap();
+        write_teams(conn, &key, &[team]).unwrap();
+        write_alliances(conn, &key, &alliances).unwrap();
+        write_oprs(conn, &key, Some(&oprs)).unwrap();
+        write_statuses(conn, &key, &statuses).unwrap();
+        for _ in 0..2 {
+            write_matches(conn, &key, std::slice::from_ref(&m)).unwrap();
+        }
+        set_etag(conn, &event_resource(key.as_ref(), "matches"), Some("\"abc\"")).unwrap();
+
+        let count = |table: &str| conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get::<_, u32>(0)).unwrap();
+        assert_eq!(count("matches"), 1);
+        assert_eq!(count("match_teams"), 6);
+        assert_eq!(count("alliance_teams"), 3);
+        assert_eq!(store.event_keys(year).unwrap(), std::slice::from_ref(&key));
+        assert_eq!(store.etag("event/2023cafr/matches").unwrap().as_deref(), Some("\"abc\""));
+
+        let (rank, opr): (u32, f64) = conn.query_row(
+            "SELECT s.qual_rank, o.opr FROM event_teams t
+             JOIN statuses s USING (event_key, team_key)
+             JOIN oprs o USING (event_key, team_key)
+             WHERE t.team_key = 'frc1280'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(rank, 4);
+        assert!((opr - 25.462).abs() < 1e-3);
+        let pick: u32 = conn.query_row(
+            "SELECT pick FROM alliance_teams WHERE event_key = ?1 AND team_key = 'frc1280'",
+            [key.as_ref()],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(pick, 1);
+
+        // Removing the event from its year removes every row and ETag written for it
+        write_events(conn, year, &[]).unwrap();
+        for table in ["events", "event_teams", "matches", "match_teams", "alliances", "alliance_teams", "oprs", "statuses", "etags"] {
+            assert_eq!(count(table), 0, "{}", table);
+        }
+        assert_eq!(count("teams"), 1);
+    }
+
+    #[actix_rt::test]
+    async fn sync_changes() {
+        let event = include_str!("../../fixtures/event_2023cafr.json");
+        let team = include_str!("../../fixtures/team_frc1280.json");
+        let mut m: Match = serde_json::from_str(include_str!("../../fixtures/match_2023cafr_qm1.json")).unwrap();
+        let api = MockApi::default();
+        api.set("events/2023", format!("[{}]", event), "\"events\"");
+        api.set("event/2023cafr/teams", format!("[{}]", team), "\"teams\"");
+        api.set("event/2023cafr/matches", serde_json::to_string(&[&m]).unwrap(), "\"matches\"");
+        api.set("event/2023cafr/alliances", "[]", "\"alliances\"");
+        api.set("event/2023cafr/oprs", include_str!("../../fixtures/event_2023cafr_oprs.json"), "\"oprs\"");
+        api.set("event/2023cafr/teams/statuses", include_str!("../../fixtures/event_2023cafr_team_statuses.json"), "\"statuses\"");
+        let ctx = Context::authenticate("key").unwrap().with_base_url(api.serve());
+        let mut store = Store::open_in_memory().unwrap();
+        let year = Year::new(2023).unwrap();
+
+        let report = store.sync(year, &ctx).await.unwrap();
+        assert_eq!(report.checked, 6);
+        assert_eq!(report.changed, [
+            "events/2023",
+            "event/2023cafr/teams",
+            "event/2023cafr/matches",
+            "event/2023cafr/alliances",
+            "event/2023cafr/oprs",
+            "event/2023cafr/teams/statuses",
+        ]);
+        assert_eq!(store.etag("event/2023cafr/oprs").unwrap().as_deref(), Some("\"oprs\""));
+        api.take_requests();
+
+        // Every resource is answered with 304 Not Modified, so nothing is rewritten
+        let report = store.sync(year, &ctx).await.unwrap();
+        assert_eq!(report.checked, 6);
+        assert!(report.changed.is_empty());
+        assert_eq!(api.take_requests().len(), 6);
+
+        // Only the resource that changed is rewritten
+        m.alliances.red.score = Some(100);
+        api.set("event/2023cafr/matches", serde_json::to_string(&[&m]).unwrap(), "\"matches 2\"");
+        let report = store.sync(year, &ctx).await.unwrap();
+        assert_eq!(report.changed, ["event/2023cafr/matches"]);
+        assert_eq!(store.etag("event/2023cafr/matches").unwrap().as_deref(), Some("\"matches 2\""));
+        let score: i32 = store.connection().query_row("SELECT red_score FROM matches", [], |row| row.get(0)).unwrap();
+        assert_eq!(score, 100);
+    }
+}
@@ -0,0 +1,348 @@
+use std::time::Duration;
+
+use awc::{Client, ClientBuilder, http::header::{CONTENT_TYPE, HeaderName}};
+use md5::{Digest, Md5};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::{
+    Error,
+    model::{
+        event::{EliminationAlliance, Event, EventKey, EventRankings},
+        matches::{Match, MatchAlliance, MatchKey},
+        team::TeamKey,
+    },
+};
+
+const BASE_ENDPOINT: &str = "https://www.thebluealliance.com/api/trusted/v1/";
+
+/// Client for the TBA Trusted API, used to write data for events that the owner of a set of
+/// trusted API credentials is authorized to manage
+pub struct TrustedClient {
+    client: Client,
+    base: Url,
+    auth_id: String,
+    auth_secret: String,
+}
+
+#[derive(Serialize)]
+struct EventInfoBody<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_code: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    playoff_type: Option<i64>,
+    webcasts: Vec<WebCastBody<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct WebCastBody<'a> {
+    #[serde(rename = "type")]
+    type_: &'a str,
+    channel: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct MatchBody<'a> {
+    comp_level: &'a str,
+    set_number: u32,
+    match_number: u32,
+    alliances: MatchAlliancesBody,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score_breakdown: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_utc: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MatchAlliancesBody {
+    red: MatchAllianceBody,
+    blue: MatchAllianceBody,
+}
+
+#[derive(Serialize)]
+struct MatchAllianceBody {
+    teams: Vec<String>,
+    score: Option<i32>,
+    surrogates: Vec<String>,
+    dqs: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RankingsBody {
+    breakdowns: Vec<String>,
+    rankings: Vec<Map<String, Value>>,
+}
+
+impl TrustedClient {
+    /// Create a new client that signs requests with the given trusted API ID and secret
+    pub fn new(auth_id: impl Into<String>, auth_secret: impl Into<String>) -> Self {
+        Self {
+            client: ClientBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .finish(),
+            base: Url::parse(BASE_ENDPOINT).expect("Trusted API base URL is valid"),
+            auth_id: auth_id.into(),
+            auth_secret: auth_secret.into(),
+        }
+    }
+
+    /// Send requests to the given base URL instead of the TBA Trusted API, e.g. a local server
+    /// used for testing. The URL should end with a trailing slash
+    pub fn with_base_url(mut self, base: Url) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Update the FIRST event code, playoff type, webcasts, and timezone of an event from the
+    /// corresponding fields of `event`
+    pub async fn update_info(&self, event: &Event) -> Result<(), Error> {
+        let body = EventInfoBody {
+            first_code: event.first_event_code.as_deref(),
+            playoff_type: event.playoff_type.map(|ty| ty.code()),
+            webcasts: event
+                .webcasts
+                .iter()
+                .map(|webcast| WebCastBody {
+                    type_: webcast.type_.as_str(),
+                    channel: &webcast.channel,
+                    file: webcast.file.as_deref(),
+                })
+                .collect(),
+            timezone: event.timezone.as_deref(),
+        };
+        self.post(&event.simple.key, "info/update", &body).await
+    }
+
+    /// Replace the alliances selected at an event, using the captain and picks of each alliance
+    pub async fn update_alliance_selections(&self, event: &EventKey, alliances: &[EliminationAlliance]) -> Result<(), Error> {
+        let body = alliances
+            .iter()
+            .map(|alliance| alliance
+                .picks
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+            )
+            .collect::<Vec<_>>();
+        self.post(event, "alliance_selections/update", &body).await
+    }
+
+    /// Create or update the given matches of an event
+    pub async fn update_matches(&self, event: &EventKey, matches: &[Match]) -> Result<(), Error> {
+        fn alliance(alliance: &MatchAlliance) -> MatchAllianceBody {
+            let keys = |keys: &[TeamKey]| keys.iter().map(ToString::to_string).collect();
+            MatchAllianceBody {
+                teams: keys(&alliance.team_keys),
+                score: alliance.score,
+                surrogates: keys(&alliance.surrogate_team_keys),
+                dqs: keys(&alliance.dq_team_keys),
+            }
+        }
+
+        let body = matches
+            .iter()
+            .map(|m| MatchBody {
+                comp_level: m.comp_level.as_str(),
+                set_number: m.set_number,
+                match_number: m.match_number,
+                alliances: MatchAlliancesBody {
+                    red: alliance(&m.alliances.red),
+                    blue: alliance(&m.alliances.blue),
+                },
+                score_breakdown: m.score_breakdown.as_ref(),
+                time_utc: m.time.map(|time| time.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            })
+            .collect::<Vec<_>>();
+        self.post(event, "matches/update", &body).await
+    }
+
+    /// Delete the given matches from an event
+    pub async fn delete_matches(&self, event: &EventKey, matches: &[MatchKey]) -> Result<(), Error> {
+        let body = matches
+            .iter()
+            .map(|key| {
+                let key: &str = key.as_ref();
+                key
+                    .split_once('_')
+                    .map_or(key, |(_, partial)| partial)
+            })
+            .collect::<Vec<_>>();
+        self.post(event, "matches/delete", &body).await
+    }
+
+    /// Replace the rankings of an event, naming each team's sort orders using the sort order
+    /// info of `rankings`
+    pub async fn update_rankings(&self, event: &EventKey, rankings: &EventRankings) -> Result<(), Error> {
+        let breakdowns = rankings
+            .sort_order_info
+            .iter()
+            .enumerate()
+            .map(|(i, info)| info.name.clone().unwrap_or_else(|| format!("Sort Order {}", i + 1)))
+            .collect::<Vec<_>>();
+        let rankings = rankings
+            .rankings
+            .iter()
+            .map(|ranking| {
+                let mut row = Map::new();
+                row.insert("team_key".to_owned(), ranking.team_key.as_ref().map(ToString::to_string).into());
+                row.insert("rank".to_owned(), ranking.rank.into());
+                row.insert("played".to_owned(), ranking.matches_played.into());
+                row.insert("dqs".to_owned(), ranking.dq.into());
+                if let Some(ref record) = ranking.record {
+                    row.insert("wins".to_owned(), record.wins.into());
+                    row.insert("losses".to_owned(), record.losses.into());
+                    row.insert("ties".to_owned(), record.ties.into());
+                }
+                for (name, val) in breakdowns.iter().zip(ranking.sort_orders.iter().flatten()) {
+                    row.insert(name.clone(), (*val).into());
+                }
+                row
+            })
+            .collect();
+        let body = RankingsBody { breakdowns, rankings };
+        self.post(event, "rankings/update", &body).await
+    }
+
+    /// Replace the list of teams attending an event
+    pub async fn update_team_list(&self, event: &EventKey, teams: &[TeamKey]) -> Result<(), Error> {
+        let body = teams
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        self.post(event, "team_list/update", &body).await
+    }
+
+    /// Add YouTube videos, identified by their video IDs, to an event
+    pub async fn add_media(&self, event: &EventKey, videos: &[String]) -> Result<(), Error> {
+        self.post(event, "media/add", &videos).await
+    }
+
+    /// Sign and send a request to `event/{event}/{path}` with the given body serialized as JSON
+    async fn post<B: Serialize + ?Sized>(&self, event: &EventKey, path: &str, body: &B) -> Result<(), Error> {
+        let url = self.base.join(&format!("event/{}/{}", event, path))?;
+        let body = serde_json::to_vec(body)?;
+        let response = self
+            .client
+            .post(url.as_str())
+            .insert_header((HeaderName::from_static("x-tba-auth-id"), self.auth_id.as_str()))
+            .insert_header((HeaderName::from_static("x-tba-auth-sig"), self.signature(url.path(), &body)))
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .send_body(body)
+            .await?;
+
+        match response.status() {
+            code if code.is_success() => Ok(()),
+            code => Err(Error::BadResponse(code)),
+        }
+    }
+
+    /// Compute the request signature for a request to the given URL path with the given body
+    fn signature(&self, path: &str, body: &[u8]) -> String {
+        let mut hasher = Md5::new();
+        hasher.update(self.auth_secret.as_bytes());
+        hasher.update(path.as_bytes());
+        hasher.update(body);
+        hex::encode(hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::ctx::mock::MockApi;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn signed_requests() {
+        let api = MockApi::default();
+        let client = TrustedClient::new("id", "secret").with_base_url(api.serve_at("api/trusted/v1/"));
+        let event: Event = serde_json::from_str(include_str!("../fixtures/event_2023cafr.json")).unwrap();
+        let m: Match = serde_json::from_str(include_str!("../fixtures/match_2023cafr_qm1.json")).unwrap();
+        let rankings: EventRankings = serde_json::from_str(include_str!("../fixtures/event_2023cafr_rankings.json")).unwrap();
+        let key = event.simple.key.clone();
+        let teams: Vec<TeamKey> = vec!["frc1280".parse().unwrap(), "frc254".parse().unwrap()];
+        let alliances: Vec<EliminationAlliance> = serde_json::from_value(json!([
+            { "name": "Alliance 1", "backup": null, "declined": [], "picks": ["frc1678", "frc1280", "frc5430"], "status": null },
+            { "name": "Alliance 2", "backup": null, "declined": [], "picks": ["frc254", "frc8033", "frc701"], "status": null },
+        ])).unwrap();
+        let deleted: Vec<MatchKey> = vec!["2023cafr_qm1".parse().unwrap(), "2023cafr_sf2m1".parse().unwrap()];
+
+        client.update_info(&event).await.unwrap();
+        client.update_alliance_selections(&key, &alliances).await.unwrap();
+        client.update_matches(&key, std::slice::from_ref(&m)).await.unwrap();
+        client.delete_matches(&key, &deleted).await.unwrap();
+        client.update_rankings(&key, &rankings).await.unwrap();
+        client.update_team_list(&key, &teams).await.unwrap();
+        client.add_media(&key, &["dQ8b3Jd9lqk".to_owned()]).await.unwrap();
+
+        let expected = [
+            ("info/update", json!({
+                "first_code": "CAFR",
+                "playoff_type": 10,
+                "webcasts": [
+                    { "type": "twitch", "channel": "firstinspires14" },
+                    { "type": "twitch", "channel": "firstinspires14" },
+                ],
+                "timezone": "America/Los_Angeles",
+            })),
+            ("alliance_selections/update", json!([
+                ["frc1678", "frc1280", "frc5430"],
+                ["frc254", "frc8033", "frc701"],
+            ])),
+            ("matches/update", json!([{
+                "comp_level": "qm",
+                "set_number": 1,
+                "match_number": 1,
+                "alliances": {
+                    "red": { "teams": ["frc1280", "frc701", "frc3189"], "score": 47, "surrogates": [], "dqs": [] },
+                    "blue": { "teams": ["frc1678", "frc5430", "frc8033"], "score": 61, "surrogates": [], "dqs": [] },
+                },
+                "score_breakdown": m.score_breakdown,
+                "time_utc": "2023-03-16T16:26:00",
+            }])),
+            ("matches/delete", json!(["qm1", "sf2m1"])),
+            ("rankings/update", json!({
+                "breakdowns": ["Ranking Score", "Avg Match", "Avg Charge Station", "Avg Auto", "Avg Endgame", "Avg Fouls"],
+                "rankings": rankings.rankings.iter().map(|ranking| {
+                    let record = ranking.record.as_ref().unwrap();
+                    let mut row = json!({
+                        "team_key": ranking.team_key.as_ref().unwrap().to_string(),
+                        "rank": ranking.rank,
+                        "played": ranking.matches_played,
+                        "dqs": ranking.dq,
+                        "wins": record.wins,
+                        "losses": record.losses,
+                        "ties": record.ties,
+                    });
+                    for (info, val) in rankings.sort_order_info.iter().zip(ranking.sort_orders.iter().flatten()) {
+                        row[info.name.as_ref().unwrap()] = json!(val);
+                    }
+                    row
+                }).collect::<Vec<_>>(),
+            })),
+            ("team_list/update", json!(["frc1280", "frc254"])),
+            ("media/add", json!(["dQ8b3Jd9lqk"])),
+        ];
+
+        let received = api.take_received();
+        assert_eq!(received.len(), expected.len());
+        for (request, (path, body)) in received.iter().zip(expected) {
+            let path = format!("event/2023cafr/{}", path);
+            assert_eq!(request.method, "POST");
+            assert_eq!(request.path, path);
+            assert_eq!(request.header("X-TBA-Auth-Id"), Some("id"));
+            let mut signature = Md5::new();
+            signature.update(format!("secret/api/trusted/v1/{}", path));
+            signature.update(&request.body);
+            assert_eq!(request.header("X-TBA-Auth-Sig"), Some(hex::encode(signature.finalize()).as_str()), "{}", path);
+            assert_eq!(serde_json::from_slice::<Value>(&request.body).unwrap(), body, "{}", path);
+        }
+    }
+}
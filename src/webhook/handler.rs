@@ -0,0 +1,112 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use actix_web::{web, HttpRequest, HttpResponse, Resource};
+
+use super::{WebhookError, WebhookMessage, SIGNATURE_HEADER};
+
+type Callback = dyn Fn(WebhookMessage) -> Pin<Box<dyn Future<Output = ()>>> + Send + Sync;
+
+/// An HTTP handler that can be mounted in an `actix-web` application to receive webhook
+/// messages, verify their signatures, and pass them to a user-supplied callback
+#[derive(Clone)]
+pub struct WebhookHandler {
+    secret: Arc<str>,
+    callback: Arc<Callback>,
+}
+
+impl WebhookHandler {
+    /// Create a new handler that verifies requests using the given webhook `secret` and awaits
+    /// `callback` for every valid message received
+    pub fn new<F, Fut>(secret: impl Into<String>, callback: F) -> Self
+    where
+        F: Fn(WebhookMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + 'static {
+        Self {
+            secret: secret.into().into(),
+            callback: Arc::new(move |msg| Box::pin(callback(msg))),
+        }
+    }
+
+    /// Decode and verify a request body, then dispatch the decoded message to the callback
+    pub async fn handle(&self, body: &[u8], signature: Option<&str>) -> Result<(), WebhookError> {
+        let msg = WebhookMessage::decode(&self.secret, body, signature)?;
+        (self.callback)(msg).await;
+        Ok(())
+    }
+
+    /// Create an `actix-web` resource that accepts POST requests at `path` and dispatches them
+    /// to this handler
+    pub fn resource(self, path: &str) -> Resource {
+        web::resource(path)
+            .app_data(web::Data::new(self))
+            .route(web::post().to(receive))
+    }
+}
+
+async fn receive(req: HttpRequest, body: web::Bytes, handler: web::Data<WebhookHandler>) -> HttpResponse {
+    let signature = req
+        .headers()
+        .get(SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok());
+    match handler.handle(&body, signature).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(WebhookError::JSON(e)) => HttpResponse::BadRequest().body(e.to_string()),
+        Err(e) => HttpResponse::Unauthorized().body(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use actix_web::{test, App};
+
+    use super::*;
+    use crate::webhook::test::sign;
+
+    #[actix_rt::test]
+    async fn receive_requests() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let received = received.clone();
+            WebhookHandler::new("hunter2", move |msg| {
+                received.lock().unwrap().push(msg);
+                async {}
+            })
+        };
+        let app = test::init_service(App::new().service(handler.resource("/webhook"))).await;
+        let post = |body: &'static [u8], signature: Option<String>| {
+            let mut request = test::TestRequest::post().uri("/webhook").set_payload(body);
+            if let Some(signature) = signature {
+                request = request.insert_header((SIGNATURE_HEADER, signature));
+            }
+            request.to_request()
+        };
+
+        let verification = br#"{"message_type": "verification", "message_data": {"verification_key": "abc123"}}"#;
+        let response = test::call_service(&app, post(verification, Some(sign("hunter2", verification)))).await;
+        assert_eq!(response.status(), 200);
+        assert!(matches!(received.lock().unwrap().pop(), Some(WebhookMessage::Verification(v)) if v.verification_key == "abc123"));
+
+        let response = test::call_service(&app, post(verification, Some(sign("hunter3", verification)))).await;
+        assert_eq!(response.status(), 401);
+        let response = test::call_service(&app, post(verification, None)).await;
+        assert_eq!(response.status(), 401);
+        assert!(received.lock().unwrap().is_empty());
+
+        let schedule = br#"{"message_type": "schedule_updated", "message_data": {"event_key": "2023casj", "first_match_time": 1680287400}}"#;
+        let response = test::call_service(&app, post(schedule, Some(sign("hunter2", schedule)))).await;
+        assert_eq!(response.status(), 200);
+        assert!(matches!(received.lock().unwrap().pop(), Some(WebhookMessage::ScheduleUpdated(s)) if s.first_match_time.is_some()));
+
+        // Message types added to TBA later are still accepted
+        let unknown = br#"{"message_type": "event_renamed", "message_data": {}}"#;
+        let response = test::call_service(&app, post(unknown, Some(sign("hunter2", unknown)))).await;
+        assert_eq!(response.status(), 200);
+        assert!(matches!(received.lock().unwrap().pop(), Some(WebhookMessage::Unknown { .. })));
+
+        let malformed = br#"{"message_type": "verification", "message_data": {}}"#;
+        let response = test::call_service(&app, post(malformed, Some(sign("hunter2", malformed)))).await;
+        assert_eq!(response.status(), 400);
+    }
+}
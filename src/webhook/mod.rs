@@ -0,0 +1,235 @@
+#[cfg(feature = "webhook-handler")]
+pub mod handler;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Deserializer};
+use sha2::Sha256;
+
+use crate::model::{
+    award::Award,
+    event::{Event, EventKey, PlayoffLevel, WebCast},
+    matches::{Match, MatchKey},
+    team::TeamKey,
+};
+
+/// Name of the HTTP header containing the hex-encoded HMAC-SHA256 signature of a webhook request
+/// body
+pub const SIGNATURE_HEADER: &str = "X-TBA-HMAC";
+
+/// A push notification sent by TBA to a registered webhook URL
+#[derive(Clone, Debug)]
+pub enum WebhookMessage {
+    UpcomingMatch(UpcomingMatch),
+    MatchScore(MatchScore),
+    MatchVideo(MatchVideo),
+    StartingCompLevel(StartingCompLevel),
+    AllianceSelection(AllianceSelection),
+    AwardsPosted(AwardsPosted),
+    ScheduleUpdated(ScheduleUpdated),
+    Verification(Verification),
+    Ping(Ping),
+    /// A message type that is not known to this crate, as sent by TBA
+    Unknown { message_type: String, message_data: serde_json::Value },
+}
+
+impl<'de> Deserialize<'de> for WebhookMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Raw {
+            message_type: String,
+            #[serde(default)]
+            message_data: serde_json::Value,
+        }
+
+        fn data<'de, T: Deserialize<'de>, E: serde::de::Error>(data: serde_json::Value) -> Result<T, E> {
+            T::deserialize(data).map_err(E::custom)
+        }
+
+        let Raw { message_type, message_data } = Raw::deserialize(deserializer)?;
+        Ok(match message_type.as_str() {
+            "upcoming_match" => Self::UpcomingMatch(data(message_data)?),
+            "match_score" => Self::MatchScore(data(message_data)?),
+            "match_video" => Self::MatchVideo(data(message_data)?),
+            "starting_comp_level" => Self::StartingCompLevel(data(message_data)?),
+            "alliance_selection" => Self::AllianceSelection(data(message_data)?),
+            "awards_posted" => Self::AwardsPosted(data(message_data)?),
+            "schedule_updated" => Self::ScheduleUpdated(data(message_data)?),
+            "verification" => Self::Verification(data(message_data)?),
+            "ping" => Self::Ping(data(message_data)?),
+            _ => return crate::model::unknown("webhook message type", &message_type, Self::Unknown {
+                message_type: message_type.clone(),
+                message_data,
+            }),
+        })
+    }
+}
+
+/// Sent a few minutes before a match is expected to start
+#[derive(Clone, Debug, Deserialize)]
+pub struct UpcomingMatch {
+    pub event_key: EventKey,
+    pub match_key: MatchKey,
+    pub event_name: String,
+    pub team_keys: Vec<TeamKey>,
+    #[serde(default, deserialize_with="crate::model::deserialize_ts_opt")]
+    pub scheduled_time: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with="crate::model::deserialize_ts_opt")]
+    pub predicted_time: Option<DateTime<Utc>>,
+    pub webcast: Option<WebCast>,
+}
+
+/// Sent when the score of a match is posted
+#[derive(Clone, Debug, Deserialize)]
+pub struct MatchScore {
+    pub event_key: EventKey,
+    pub match_key: MatchKey,
+    pub event_name: String,
+    #[serde(rename="match")]
+    pub match_: Match,
+}
+
+/// Sent when a video is added to a match
+#[derive(Clone, Debug, Deserialize)]
+pub struct MatchVideo {
+    pub event_key: Option<EventKey>,
+    pub match_key: Option<MatchKey>,
+    pub event_name: String,
+    #[serde(rename="match")]
+    pub match_: Match,
+}
+
+/// Sent when the first match of a competition level is about to start
+#[derive(Clone, Debug, Deserialize)]
+pub struct StartingCompLevel {
+    pub event_key: EventKey,
+    pub event_name: String,
+    pub comp_level: PlayoffLevel,
+    #[serde(default, deserialize_with="crate::model::deserialize_ts_opt")]
+    pub scheduled_time: Option<DateTime<Utc>>,
+}
+
+/// Sent when alliance selection at an event has finished
+#[derive(Clone, Debug, Deserialize)]
+pub struct AllianceSelection {
+    pub event_key: EventKey,
+    pub event_name: String,
+    pub event: Box<Event>,
+}
+
+/// Sent when awards are posted for an event
+#[derive(Clone, Debug, Deserialize)]
+pub struct AwardsPosted {
+    pub event_key: EventKey,
+    pub event_name: String,
+    pub awards: Vec<Award>,
+}
+
+/// Sent when the match schedule of an event is posted or changed
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScheduleUpdated {
+    pub event_key: EventKey,
+    pub event_name: Option<String>,
+    #[serde(default, deserialize_with="crate::model::deserialize_ts_opt")]
+    pub first_match_time: Option<DateTime<Utc>>,
+}
+
+/// Sent once when a webhook is registered, containing the key that must be entered on TBA to
+/// confirm ownership of the URL
+#[derive(Clone, Debug, Deserialize)]
+pub struct Verification {
+    pub verification_key: String,
+}
+
+/// Test message sent on request from the TBA account page
+#[derive(Clone, Debug, Deserialize)]
+pub struct Ping {
+    pub title: String,
+    pub desc: String,
+}
+
+/// Errors that can occur while decoding a webhook request
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Webhook request is missing the {} header", SIGNATURE_HEADER)]
+    MissingSignature,
+    #[error("Webhook request signature does not match the request body")]
+    BadSignature,
+    #[error("Failed to deserialize webhook message: {0}")]
+    JSON(#[from] serde_json::Error),
+}
+
+/// Check that `signature`, the hex-encoded value of the [SIGNATURE_HEADER] header, is the
+/// HMAC-SHA256 of `body` keyed with the webhook `secret`
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature.trim()) else { return false };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+impl WebhookMessage {
+    /// Verify the signature of a webhook request body and deserialize the message it contains
+    pub fn decode(secret: &str, body: &[u8], signature: Option<&str>) -> Result<Self, WebhookError> {
+        let signature = signature.ok_or(WebhookError::MissingSignature)?;
+        if !verify_signature(secret, body, signature) {
+            return Err(WebhookError::BadSignature)
+        }
+
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    pub(super) fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn decode_signed() {
+        let body = br#"{
+            "message_type": "upcoming_match",
+            "message_data": {
+                "event_key": "2023casj",
+                "match_key": "2023casj_qm12",
+                "event_name": "Silicon Valley Regional",
+                "team_keys": ["frc1280", "frc254", "frc604", "frc649", "frc846", "frc971"],
+                "scheduled_time": 1680287400,
+                "predicted_time": 1680287520
+            }
+        }"#;
+        let signature = sign("hunter2", body);
+        match WebhookMessage::decode("hunter2", body, Some(&signature)).unwrap() {
+            WebhookMessage::UpcomingMatch(m) => {
+                assert_eq!(m.match_key.to_string(), "2023casj_qm12");
+                assert_eq!(m.team_keys.len(), 6);
+                assert!(m.webcast.is_none());
+            },
+            other => panic!("Decoded wrong message type {:?}", other),
+        }
+
+        assert!(matches!(
+            WebhookMessage::decode("hunter3", body, Some(&signature)),
+            Err(WebhookError::BadSignature)
+        ));
+        assert!(matches!(
+            WebhookMessage::decode("hunter2", body, None),
+            Err(WebhookError::MissingSignature)
+        ));
+
+        let body = br#"{"message_type": "event_renamed", "message_data": {"event_key": "2023casj"}}"#;
+        match WebhookMessage::decode("hunter2", body, Some(&sign("hunter2", body))).unwrap() {
+            WebhookMessage::Unknown { message_type, message_data } => {
+                assert_eq!(message_type, "event_renamed");
+                assert_eq!(message_data["event_key"], "2023casj");
+            },
+            other => panic!("Decoded wrong message type {:?}", other),
+        }
+    }
+}